@@ -0,0 +1,52 @@
+#![no_std]
+
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+#[derive(TopEncode, TopDecode)]
+pub struct PriceFeed<M: ManagedTypeApi> {
+    pub round_id: u32,
+    pub price: BigUint<M>,
+    pub decimals: u8,
+}
+
+/// Stands in for the price aggregator in the stablecoin's mandos scenarios.
+/// Answers with the last feed set for the ticker, whatever the quote ticker
+#[elrond_wasm::contract]
+pub trait PriceAggregatorMock {
+    #[init]
+    fn init(&self) {}
+
+    #[endpoint(setLatestPriceFeed)]
+    fn set_latest_price_feed(
+        &self,
+        from: ManagedBuffer,
+        round_id: u32,
+        price: BigUint,
+        decimals: u8,
+    ) {
+        self.price_feed(&from).set(&PriceFeed {
+            round_id,
+            price,
+            decimals,
+        });
+    }
+
+    #[view(latestPriceFeedOptional)]
+    fn latest_price_feed_optional(
+        &self,
+        from: ManagedBuffer,
+        to: ManagedBuffer,
+    ) -> OptionalResult<MultiResult5<u32, ManagedBuffer, ManagedBuffer, BigUint, u8>> {
+        let mapper = self.price_feed(&from);
+        if mapper.is_empty() {
+            return OptionalResult::None;
+        }
+
+        let feed = mapper.get();
+        OptionalResult::Some((feed.round_id, from, to, feed.price, feed.decimals).into())
+    }
+
+    #[storage_mapper("priceFeed")]
+    fn price_feed(&self, from: &ManagedBuffer) -> SingleValueMapper<PriceFeed<Self::Api>>;
+}
@@ -0,0 +1,4 @@
+#![no_std]
+
+pub use price_aggregator_mock::*;
+pub use elrond_wasm_output::*;
@@ -1,11 +1,30 @@
 elrond_wasm::imports!();
 elrond_wasm::derive_imports!();
 
-use crate::math::ONE;
+use crate::{
+    hedging_token::HedgingTokenAttributes, math::ONE, pause::PausableAction, twap::PricedOperation,
+};
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum ForceCloseOrdering {
+    OldestFirst,
+    HighestLeverageFirst,
+}
+
+impl ForceCloseOrdering {
+    pub fn from_index(index: u64) -> Option<Self> {
+        match index {
+            0 => Some(ForceCloseOrdering::OldestFirst),
+            1 => Some(ForceCloseOrdering::HighestLeverageFirst),
+            _ => None,
+        }
+    }
+}
 
 pub struct HedgerWithdrawAmountFeeSplit<M: ManagedTypeApi> {
     pub withdraw_amount: BigUint<M>,
     pub fees_amount: BigUint<M>,
+    pub collateral_value_in_dollars: BigUint<M>,
 }
 
 pub struct HedgerRewardAmountsTokensPair<M: ManagedTypeApi> {
@@ -13,14 +32,35 @@ pub struct HedgerRewardAmountsTokensPair<M: ManagedTypeApi> {
     pub liq_tokens_amount: BigUint<M>,
 }
 
+/// PnL is unrealized for the whole covered amount, so it is not capped at the deposit.
+/// liquidation_price is None if the position is liquidatable at any price.
+/// All the values already take the accrued funding out of the deposit.
+#[derive(TypeAbi, TopEncode)]
+pub struct HedgingPositionHealth<M: ManagedTypeApi> {
+    pub oracle_price: BigUint<M>,
+    pub margin_ratio: BigUint<M>,
+    pub leverage: BigUint<M>,
+    pub is_pnl_negative: bool,
+    pub unrealized_pnl_in_collateral: BigUint<M>,
+    pub unrealized_pnl_in_dollars: BigUint<M>,
+    pub liquidation_price: Option<BigUint<M>>,
+    pub withdraw_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub accrued_funding: BigUint<M>,
+}
+
 #[derive(TypeAbi, TopEncode, TopDecode)]
 pub struct HedgingPosition<M: ManagedTypeApi> {
     pub collateral_id: TokenIdentifier<M>,
     pub deposit_amount: BigUint<M>,
     pub covered_amount: BigUint<M>,
     pub oracle_value_at_deposit_time: BigUint<M>,
+    pub covered_value_in_stablecoin: BigUint<M>,
     pub creation_timestamp: u64,
     pub withdraw_amount_after_force_close: Option<BigUint<M>>,
+    pub stop_loss_price: Option<BigUint<M>>,
+    pub take_profit_price: Option<BigUint<M>>,
+    pub funding_index: BigUint<M>,
 }
 
 impl<M: ManagedTypeApi> HedgingPosition<M> {
@@ -32,13 +72,19 @@ impl<M: ManagedTypeApi> HedgingPosition<M> {
 
 #[elrond_wasm::module]
 pub trait HedgingAgentsModule:
-    crate::fees::FeesModule
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
     + crate::hedging_token::HedgingTokenModule
     + crate::liquidity_token::LiquidityTokenModule
     + crate::math::MathModule
+    + crate::pause::PauseModule
     + crate::pools::PoolsModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
     + crate::token_common::TokenCommonModule
+    + crate::twap::TwapModule
 {
     #[payable("*")]
     #[endpoint(openHedgingPosition)]
@@ -50,13 +96,17 @@ pub trait HedgingAgentsModule:
         max_oracle_value: BigUint,
     ) -> SCResult<()> {
         self.require_collateral_in_whitelist(&payment_token)?;
+        self.require_not_paused(&payment_token, PausableAction::HedgingOpen)?;
 
-        let collateral_value_in_dollars = self.get_collateral_value_in_dollars(&payment_token)?;
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&payment_token, PricedOperation::HedgingOpen)?;
         require!(
             collateral_value_in_dollars <= max_oracle_value,
             "Oracle value is higher than the provided max"
         );
 
+        let funding_index = self.update_funding_index(&payment_token);
+        self.refresh_fee_configuration_if_stale(&payment_token);
         let mut pool = self.get_pool(&payment_token);
         let target_hedge_amount = self.calculate_target_hedge_amount(&pool.collateral_amount);
         require!(
@@ -74,13 +124,12 @@ pub trait HedgingAgentsModule:
             "Position would go over target hedge amount"
         );
 
-        let collateral_precision = self.get_collateral_precision(&payment_token);
-        let amount_to_cover_in_stablecoin = self.multiply(
-            &collateral_value_in_dollars,
+        let amount_to_cover_in_stablecoin = self.get_collateral_value_in_stablecoin(
+            &payment_token,
             &amount_to_cover,
-            &collateral_precision,
+            &collateral_value_in_dollars,
         );
-        pool.total_covered_value_in_stablecoin += amount_to_cover_in_stablecoin;
+        pool.total_covered_value_in_stablecoin += &amount_to_cover_in_stablecoin;
 
         let transaction_fees_percentage =
             self.get_hedging_position_open_transaction_fees_percentage(&payment_token);
@@ -95,20 +144,35 @@ pub trait HedgingAgentsModule:
             deposit_amount: collateral_amount,
             covered_amount: amount_to_cover,
             oracle_value_at_deposit_time: collateral_value_in_dollars,
+            covered_value_in_stablecoin: amount_to_cover_in_stablecoin,
             creation_timestamp: self.blockchain().get_block_timestamp(),
             withdraw_amount_after_force_close: None,
+            stop_loss_price: None,
+            take_profit_price: None,
+            funding_index,
         };
         self.require_under_max_leverage(&hedging_position)?;
 
         self.accumulated_tx_fees(&payment_token)
-            .update(|accumulated_fees| *accumulated_fees += fees_amount_in_collateral);
+            .update(|accumulated_fees| *accumulated_fees += &fees_amount_in_collateral);
 
         let caller = self.blockchain().get_caller();
-        let nft_nonce = self.create_hedging_token();
+        let nft_nonce =
+            self.create_hedging_token(&self.get_hedging_token_attributes(&hedging_position));
         self.send_hedging_token(&caller, nft_nonce);
 
         self.set_pool(&payment_token, &pool);
-        self.hedging_position(nft_nonce).set(&hedging_position);
+        self.set_hedging_position(nft_nonce, &hedging_position);
+        self.add_to_position_indexes(nft_nonce, &payment_token, &caller);
+
+        self.emit_open_hedging_position_event(
+            nft_nonce,
+            &payment_token,
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+            &fees_amount_in_collateral,
+            &hedging_position.oracle_value_at_deposit_time,
+        );
 
         Ok(())
     }
@@ -116,6 +180,8 @@ pub trait HedgingAgentsModule:
     #[payable("*")]
     #[endpoint(addMargin)]
     fn add_margin(&self) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
         let nr_required_transfers = 2;
         let transfers: Vec<EsdtTokenPayment<Self::Api>> =
             self.call_value().all_esdt_transfers().into_iter().collect();
@@ -133,28 +199,31 @@ pub trait HedgingAgentsModule:
             "First token should be the hedging NFT"
         );
 
-        let nft_nonce = first_transfer.token_nonce;
-        self.hedging_position(nft_nonce).update(|hedging_pos| {
-            require!(
-                second_transfer.token_identifier == hedging_pos.collateral_id,
-                "Second token should be the collateral for the position"
-            );
-            self.require_not_closed(hedging_pos)?;
-
-            hedging_pos.deposit_amount += &second_transfer.amount;
-            self.require_under_max_leverage(hedging_pos)?;
+        self.add_margin_to_position(
+            first_transfer.token_nonce,
+            &second_transfer.token_identifier,
+            &second_transfer.amount,
+        )
+    }
 
-            Ok(())
-        })?;
-        self.update_pool(&second_transfer.token_identifier, |pool| {
-            pool.collateral_reserves += &second_transfer.amount;
-        });
+    #[payable("*")]
+    #[endpoint(increaseCoverage)]
+    fn increase_coverage(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+        additional_amount_to_cover: BigUint,
+        max_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
 
-        // return the nft
-        let caller = self.blockchain().get_caller();
-        self.send_hedging_token(&caller, nft_nonce);
+        let hedging_token_id = self.hedging_token_id().get();
+        require!(
+            payment_token == hedging_token_id,
+            "Token should be the hedging NFT"
+        );
 
-        Ok(())
+        self.increase_position_coverage(payment_nonce, additional_amount_to_cover, max_oracle_value)
     }
 
     #[payable("*")]
@@ -165,43 +234,15 @@ pub trait HedgingAgentsModule:
         #[payment_nonce] payment_nonce: u64,
         amount_to_remove: BigUint,
     ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
         let hedging_token_id = self.hedging_token_id().get();
         require!(
             payment_token == hedging_token_id,
             "Token should be the hedging NFT"
         );
 
-        let collateral_id = self.hedging_position(payment_nonce).update(|hedging_pos| {
-            require!(
-                amount_to_remove < hedging_pos.deposit_amount,
-                "Remove amount higher than total deposit"
-            );
-            self.require_not_closed(hedging_pos)?;
-
-            hedging_pos.deposit_amount -= &amount_to_remove;
-            self.require_under_max_leverage(hedging_pos)?;
-
-            Ok(hedging_pos.collateral_id.clone())
-        })?;
-        self.update_pool(&payment_token, |pool| {
-            require!(
-                amount_to_remove <= pool.collateral_reserves,
-                "Not enough reserves in pool"
-            );
-
-            pool.collateral_reserves -= &amount_to_remove;
-
-            Ok(())
-        })?;
-
-        let caller = self.blockchain().get_caller();
-        self.send()
-            .direct(&caller, &collateral_id, 0, &amount_to_remove, &[]);
-
-        // return the nft
-        self.send_hedging_token(&caller, payment_nonce);
-
-        Ok(())
+        self.remove_margin_from_position(payment_nonce, &amount_to_remove)
     }
 
     #[payable("*")]
@@ -212,38 +253,185 @@ pub trait HedgingAgentsModule:
         #[payment_nonce] payment_nonce: u64,
         min_oracle_value: BigUint,
     ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
         let hedging_token_id = self.hedging_token_id().get();
         require!(
             payment_token == hedging_token_id,
             "May only pay with Hedging NFT"
         );
+
+        self.close_and_withdraw_position(payment_nonce, min_oracle_value)
+    }
+
+    // The NFT stays in the contract until withdrawn, and the depositor may manage
+    // the position through the *Deposited endpoints without sending the NFT each time.
+    // Keepers and global settlement work the same whether the NFT is deposited or not
+    #[payable("*")]
+    #[endpoint(depositHedgingToken)]
+    fn deposit_hedging_token(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+    ) -> SCResult<()> {
+        let hedging_token_id = self.hedging_token_id().get();
+        require!(
+            payment_token == hedging_token_id,
+            "Token should be the hedging NFT"
+        );
         self.require_not_liquidated(payment_nonce)?;
 
-        let hedging_position = self.hedging_position(payment_nonce).get();
-        let withdraw_split = match hedging_position.withdraw_amount_after_force_close {
-            Some(withdraw_amount) => self
-                .calculate_withdraw_amounts_split(&hedging_position.collateral_id, withdraw_amount),
-            None => {
-                self.close_position(&hedging_position)?;
-
-                let withdraw_amount = self.get_withdraw_amount_and_update_fees(
-                    &hedging_position,
-                    Some(min_oracle_value),
-                )?;
-                let split = self.calculate_withdraw_amounts_split(
-                    &hedging_position.collateral_id,
-                    withdraw_amount.clone(),
-                );
+        let caller = self.blockchain().get_caller();
+        let collateral_id = self.hedging_position(payment_nonce).get().collateral_id;
+        self.move_position_owner(payment_nonce, &caller);
+        self.deposited_hedging_token_owner(payment_nonce)
+            .set(&caller);
 
-                split
-            }
-        };
+        self.emit_deposit_hedging_token_event(payment_nonce, &collateral_id);
+
+        Ok(())
+    }
+
+    #[endpoint(withdrawHedgingToken)]
+    fn withdraw_hedging_token(&self, nft_nonce: u64) -> SCResult<()> {
+        let caller = self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.deposited_hedging_token_owner(nft_nonce).clear();
+        self.send_hedging_token(&caller, nft_nonce);
+
+        let collateral_id = self.hedging_position(nft_nonce).get().collateral_id;
+        self.emit_withdraw_hedging_token_event(nft_nonce, &collateral_id);
+
+        Ok(())
+    }
+
+    #[payable("*")]
+    #[endpoint(addMarginDeposited)]
+    fn add_margin_deposited(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_amount] payment_amount: BigUint,
+        nft_nonce: u64,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.add_margin_to_position(nft_nonce, &payment_token, &payment_amount)
+    }
+
+    #[endpoint(removeMarginDeposited)]
+    fn remove_margin_deposited(&self, nft_nonce: u64, amount_to_remove: BigUint) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.remove_margin_from_position(nft_nonce, &amount_to_remove)
+    }
+
+    #[endpoint(closeDepositedHedgingPosition)]
+    fn close_deposited_hedging_position(
+        &self,
+        nft_nonce: u64,
+        min_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.close_and_withdraw_position(nft_nonce, min_oracle_value)
+    }
+
+    #[endpoint(increaseCoverageDeposited)]
+    fn increase_coverage_deposited(
+        &self,
+        nft_nonce: u64,
+        additional_amount_to_cover: BigUint,
+        max_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.increase_position_coverage(nft_nonce, additional_amount_to_cover, max_oracle_value)
+    }
+
+    #[endpoint(setCloseOrdersDeposited)]
+    fn set_close_orders_deposited(
+        &self,
+        nft_nonce: u64,
+        stop_loss_price: BigUint,
+        take_profit_price: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.set_position_close_orders(nft_nonce, stop_loss_price, take_profit_price)
+    }
+
+    #[endpoint(partialCloseDepositedHedgingPosition)]
+    fn partial_close_deposited_hedging_position(
+        &self,
+        nft_nonce: u64,
+        amount_to_close: BigUint,
+        min_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.partial_close_position(nft_nonce, amount_to_close, min_oracle_value)
+    }
+
+    // internal close, the nft is either paid or deposited
+    fn close_and_withdraw_position(
+        &self,
+        payment_nonce: u64,
+        min_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_not_liquidated(payment_nonce)?;
+
+        let mut hedging_position = self.hedging_position(payment_nonce).get();
+        self.settle_funding(&mut hedging_position);
+        self.refresh_fee_configuration_if_stale(&hedging_position.collateral_id);
+
+        let (withdraw_split, opt_fees_amount, opt_oracle_price) =
+            match &hedging_position.withdraw_amount_after_force_close {
+                Some(withdraw_amount) => {
+                    self.pending_force_close_withdrawals(&hedging_position.collateral_id)
+                        .update(|pending| *pending -= withdraw_amount);
+
+                    (
+                        self.calculate_withdraw_amounts_split(
+                            &hedging_position.collateral_id,
+                            withdraw_amount.clone(),
+                        ),
+                        None,
+                        None,
+                    )
+                }
+                None => {
+                    self.close_position(&hedging_position)?;
+
+                    let withdraw_amount_fee_split = self.get_withdraw_amount_and_update_fees(
+                        &hedging_position,
+                        Some(min_oracle_value),
+                    )?;
+                    let split = self.calculate_withdraw_amounts_split(
+                        &hedging_position.collateral_id,
+                        withdraw_amount_fee_split.withdraw_amount,
+                    );
+
+                    (
+                        split,
+                        Some(withdraw_amount_fee_split.fees_amount),
+                        Some(withdraw_amount_fee_split.collateral_value_in_dollars),
+                    )
+                }
+            };
 
         self.update_pool(&hedging_position.collateral_id, |pool| {
             pool.collateral_reserves -= &withdraw_split.collateral_amount;
         });
 
-        self.hedging_position(payment_nonce).clear();
+        self.clear_hedging_position(payment_nonce);
+        self.remove_from_position_indexes(payment_nonce, &hedging_position.collateral_id);
+        self.deposited_hedging_token_owner(payment_nonce).clear();
         self.burn_hedging_token(payment_nonce);
 
         let caller = self.blockchain().get_caller();
@@ -264,151 +452,1016 @@ pub trait HedgingAgentsModule:
             );
         }
 
+        self.emit_close_hedging_position_event(
+            payment_nonce,
+            &hedging_position.collateral_id,
+            &withdraw_split.collateral_amount,
+            &liq_tokens_amount,
+            opt_fees_amount,
+            opt_oracle_price,
+        );
+
         Ok(())
     }
 
-    // private
-
-    // deduplicates code for close, force-close and liquidate
-    fn close_position(&self, hedging_position: &HedgingPosition<Self::Api>) -> SCResult<()> {
-        self.require_not_closed(hedging_position)?;
-
-        let mut pool = self.get_pool(&hedging_position.collateral_id);
+    // a price of 0 removes the respective order
+    #[payable("*")]
+    #[endpoint(setCloseOrders)]
+    fn set_close_orders(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+        stop_loss_price: BigUint,
+        take_profit_price: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
 
-        let current_time = self.blockchain().get_block_timestamp();
-        let time_diff = current_time - hedging_position.creation_timestamp;
-        let min_hedging_period_seconds = self.min_hedging_period_seconds().get();
+        let hedging_token_id = self.hedging_token_id().get();
         require!(
-            time_diff >= min_hedging_period_seconds,
-            "Trying to close too early"
-        );
-
-        let collateral_precision = self.get_collateral_precision(&hedging_position.collateral_id);
-        let amount_to_cover_in_stablecoin = self.multiply(
-            &hedging_position.oracle_value_at_deposit_time,
-            &hedging_position.covered_amount,
-            &collateral_precision,
+            payment_token == hedging_token_id,
+            "Token should be the hedging NFT"
         );
-        pool.total_covered_value_in_stablecoin -= amount_to_cover_in_stablecoin;
-        pool.total_collateral_covered -= &hedging_position.covered_amount;
-
-        self.set_pool(&hedging_position.collateral_id, &pool);
 
-        Ok(())
+        self.set_position_close_orders(payment_nonce, stop_loss_price, take_profit_price)
     }
 
-    fn get_withdraw_amount_and_update_fees(
+    #[payable("*")]
+    #[endpoint(partialCloseHedgingPosition)]
+    fn partial_close_hedging_position(
         &self,
-        hedging_position: &HedgingPosition<Self::Api>,
-        opt_min_oracle_value: Option<BigUint>,
-    ) -> SCResult<BigUint> {
-        let withdraw_amount_fees_pair =
-            self.calculate_withdraw_and_fee_amount(hedging_position, opt_min_oracle_value)?;
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+        amount_to_close: BigUint,
+        min_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
 
-        self.accumulated_tx_fees(&hedging_position.collateral_id)
-            .update(|accumulated_fees| *accumulated_fees += &withdraw_amount_fees_pair.fees_amount);
+        let hedging_token_id = self.hedging_token_id().get();
+        require!(
+            payment_token == hedging_token_id,
+            "May only pay with Hedging NFT"
+        );
 
-        Ok(withdraw_amount_fees_pair.withdraw_amount)
+        self.partial_close_position(payment_nonce, amount_to_close, min_oracle_value)
     }
 
-    fn calculate_withdraw_and_fee_amount(
+    #[view(getHedgingPositionHealth)]
+    fn get_hedging_position_health(
         &self,
-        hedging_position: &HedgingPosition<Self::Api>,
-        opt_min_oracle_value: Option<BigUint>,
-    ) -> SCResult<HedgerWithdrawAmountFeeSplit<Self::Api>> {
-        let collateral_value_in_dollars =
-            self.get_collateral_value_in_dollars(&hedging_position.collateral_id)?;
-        if let Some(min_oracle_value) = opt_min_oracle_value {
-            require!(
-                collateral_value_in_dollars >= min_oracle_value,
-                "Oracle value is lower than the provided min"
-            );
-        }
+        nft_nonce: u64,
+    ) -> SCResult<HedgingPositionHealth<Self::Api>> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        self.require_not_closed(&hedging_position)?;
+
+        let accrued_funding = self.calculate_accrued_funding(&hedging_position);
+        hedging_position.deposit_amount -= &accrued_funding;
+
+        let collateral_id = &hedging_position.collateral_id;
+        let liquidation_oracle_price =
+            self.get_collateral_price(collateral_id, PricedOperation::Liquidation)?;
+        let margin_ratio =
+            self.calculate_margin_ratio(&hedging_position, &liquidation_oracle_price);
+        let leverage = self.calculate_leverage(
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
 
+        let oracle_price =
+            self.get_collateral_price(collateral_id, PricedOperation::HedgingClose)?;
         let price_ratio = self.calculate_ratio(
             &hedging_position.oracle_value_at_deposit_time,
-            &collateral_value_in_dollars,
+            &oracle_price,
         );
-
-        // withdraw_amount = x + y * (1 - initial_oracle / current_oracle),
-        // where x is deposit_amount and y is amount_to_cover
         let one = BigUint::from(ONE);
-        let base_withdraw_amount = if price_ratio <= one {
-            let factor = &one - &price_ratio;
-            let extra_amount = self.multiply(&factor, &hedging_position.covered_amount, &one);
-
-            &hedging_position.deposit_amount + &extra_amount
+        let is_pnl_negative = price_ratio > one;
+        let pnl_factor = if is_pnl_negative {
+            &price_ratio - &one
         } else {
-            let factor = &price_ratio - &one;
-            let deducted_amount = self.multiply(&factor, &hedging_position.covered_amount, &one);
-
-            &hedging_position.deposit_amount - &deducted_amount
+            &one - &price_ratio
         };
+        let unrealized_pnl_in_collateral =
+            self.multiply(&pnl_factor, &hedging_position.covered_amount, &one);
+        let unrealized_pnl_in_dollars = self.get_collateral_value_in_stablecoin(
+            collateral_id,
+            &unrealized_pnl_in_collateral,
+            &oracle_price,
+        );
 
-        let transaction_fees_percentage = self
-            .get_hedging_position_close_transaction_fees_percentage(
-                &hedging_position.collateral_id,
-            );
-        let fees_amount =
-            self.calculate_percentage_of(&transaction_fees_percentage, &base_withdraw_amount);
-        let withdraw_amount = &base_withdraw_amount - &fees_amount;
-
-        Ok(HedgerWithdrawAmountFeeSplit {
-            withdraw_amount,
-            fees_amount,
+        let withdraw_amount_fee_split =
+            self.calculate_withdraw_and_fee_amount_at_price(&hedging_position, oracle_price);
+
+        Ok(HedgingPositionHealth {
+            oracle_price: withdraw_amount_fee_split.collateral_value_in_dollars,
+            margin_ratio,
+            leverage,
+            is_pnl_negative,
+            unrealized_pnl_in_collateral,
+            unrealized_pnl_in_dollars,
+            liquidation_price: self.calculate_liquidation_price(&hedging_position),
+            withdraw_amount: withdraw_amount_fee_split.withdraw_amount,
+            fees_amount: withdraw_amount_fee_split.fees_amount,
+            accrued_funding,
         })
     }
 
-    fn calculate_withdraw_amounts_split(
-        &self,
-        collateral_id: &TokenIdentifier,
-        full_withdraw_amount: BigUint,
-    ) -> HedgerRewardAmountsTokensPair<Self::Api> {
-        let reserves = self.get_pool_reserves(collateral_id);
-        if full_withdraw_amount <= reserves {
-            HedgerRewardAmountsTokensPair {
-                collateral_amount: full_withdraw_amount,
-                liq_tokens_amount: BigUint::zero(),
-            }
-        } else {
-            let collateral_amount_over_reserves = &full_withdraw_amount - &reserves;
-            let collateral_precision = self.get_collateral_precision(collateral_id);
-            let amount_in_liq_tokens =
-                self.collateral_to_liq_tokens(collateral_id, &collateral_amount_over_reserves, &collateral_precision);
+    #[view(getAccruedFunding)]
+    fn get_accrued_funding(&self, nft_nonce: u64) -> SCResult<BigUint> {
+        self.require_not_liquidated(nft_nonce)?;
 
-            HedgerRewardAmountsTokensPair {
-                collateral_amount: reserves,
-                liq_tokens_amount: amount_in_liq_tokens,
-            }
-        }
+        let hedging_position = self.hedging_position(nft_nonce).get();
+        Ok(self.calculate_accrued_funding(&hedging_position))
     }
 
-    #[inline(always)]
-    fn calculate_leverage(
+    #[view(getOpenPositions)]
+    fn get_open_positions(
         &self,
-        collateral_amount: &BigUint,
-        amount_to_cover: &BigUint,
-    ) -> BigUint {
-        // x + y / x
-        self.calculate_ratio(&(collateral_amount + amount_to_cover), collateral_amount)
+        collateral_id: TokenIdentifier,
+        from_index: usize,
+        count: usize,
+    ) -> MultiResultVec<u64> {
+        self.open_position_nonces(&collateral_id)
+            .iter()
+            .skip(from_index)
+            .take(count)
+            .collect()
     }
 
-    fn require_under_max_leverage(
+    #[view(getOpenPositionsCount)]
+    fn get_open_positions_count(&self, collateral_id: TokenIdentifier) -> usize {
+        self.open_position_nonces(&collateral_id).len()
+    }
+
+    #[view(getPositionsOfOwner)]
+    fn get_positions_of_owner(
         &self,
-        hedging_position: &HedgingPosition<Self::Api>,
-    ) -> SCResult<()> {
-        let max_leverage = self.max_leverage(&hedging_position.collateral_id).get();
-        let position_leverage = self.calculate_leverage(
-            &hedging_position.deposit_amount,
-            &hedging_position.covered_amount,
-        );
-        require!(position_leverage <= max_leverage, "Leverage too high");
+        owner: ManagedAddress,
+        from_index: usize,
+        count: usize,
+    ) -> MultiResultVec<u64> {
+        self.owner_position_nonces(&owner)
+            .iter()
+            .skip(from_index)
+            .take(count)
+            .collect()
+    }
 
-        Ok(())
+    #[view(getPositionsOfOwnerCount)]
+    fn get_positions_of_owner_count(&self, owner: ManagedAddress) -> usize {
+        self.owner_position_nonces(&owner).len()
     }
 
-    fn require_not_closed(&self, hedging_position: &HedgingPosition<Self::Api>) -> SCResult<()> {
-        require!(!hedging_position.is_closed(), "Position already closed");
+    // private
+
+    // the open fee for the increment is deducted from the position's deposit
+    fn increase_position_coverage(
+        &self,
+        nft_nonce: u64,
+        additional_amount_to_cover: BigUint,
+        max_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        require!(
+            additional_amount_to_cover > 0,
+            "Additional amount to cover must be positive"
+        );
+        self.require_not_liquidated(nft_nonce)?;
+
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        let collateral_id = hedging_position.collateral_id.clone();
+        self.require_not_closed(&hedging_position)?;
+        self.require_not_paused(&collateral_id, PausableAction::HedgingOpen)?;
+        self.settle_funding(&mut hedging_position);
+        self.refresh_fee_configuration_if_stale(&collateral_id);
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::HedgingOpen)?;
+        require!(
+            collateral_value_in_dollars <= max_oracle_value,
+            "Oracle value is higher than the provided max"
+        );
+
+        let mut pool = self.get_pool(&collateral_id);
+        let target_hedge_amount = self.calculate_target_hedge_amount(&pool.collateral_amount);
+        require!(
+            pool.total_collateral_covered <= target_hedge_amount,
+            "Over target hedge amount, no coverage may be added"
+        );
+
+        pool.total_collateral_covered += &additional_amount_to_cover;
+        require!(
+            pool.total_collateral_covered <= pool.collateral_amount,
+            "Trying to cover too much collateral"
+        );
+        require!(
+            pool.total_collateral_covered <= target_hedge_amount,
+            "Position would go over target hedge amount"
+        );
+
+        let additional_amount_in_stablecoin = self.get_collateral_value_in_stablecoin(
+            &collateral_id,
+            &additional_amount_to_cover,
+            &collateral_value_in_dollars,
+        );
+        pool.total_covered_value_in_stablecoin += &additional_amount_in_stablecoin;
+
+        let fees_amount_in_collateral = self.calculate_coverage_increase_fees_amount(
+            &hedging_position,
+            &additional_amount_to_cover,
+        );
+        require!(
+            fees_amount_in_collateral < hedging_position.deposit_amount,
+            "Deposit too low to pay the fees"
+        );
+
+        pool.collateral_reserves -= &fees_amount_in_collateral;
+        hedging_position.deposit_amount -= &fees_amount_in_collateral;
+
+        // value-weighted average of the old and new oracle values
+        let new_covered_amount = &hedging_position.covered_amount + &additional_amount_to_cover;
+        let total_value = &(&hedging_position.oracle_value_at_deposit_time
+            * &hedging_position.covered_amount)
+            + &(&collateral_value_in_dollars * &additional_amount_to_cover);
+        hedging_position.oracle_value_at_deposit_time = &total_value / &new_covered_amount;
+        hedging_position.covered_amount = new_covered_amount;
+        hedging_position.covered_value_in_stablecoin += &additional_amount_in_stablecoin;
+        self.require_under_max_leverage(&hedging_position)?;
+
+        self.accumulated_tx_fees(&collateral_id)
+            .update(|accumulated_fees| *accumulated_fees += &fees_amount_in_collateral);
+
+        self.set_pool(&collateral_id, &pool);
+        let new_nft_nonce = self.refresh_hedging_token(nft_nonce, &hedging_position);
+
+        let caller = self.blockchain().get_caller();
+        self.return_hedging_token(&caller, new_nft_nonce);
+
+        self.emit_increase_coverage_event(
+            new_nft_nonce,
+            &collateral_id,
+            &additional_amount_to_cover,
+            &fees_amount_in_collateral,
+            &collateral_value_in_dollars,
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+            &hedging_position.oracle_value_at_deposit_time,
+        );
+
+        Ok(())
+    }
+
+    // Opening charges the fees on the deposit, so the added coverage is charged
+    // on the part of the deposit that backs it once added
+    fn calculate_coverage_increase_fees_amount(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        additional_amount_to_cover: &BigUint,
+    ) -> BigUint {
+        let new_covered_amount = &hedging_position.covered_amount + additional_amount_to_cover;
+        let backing_deposit_amount =
+            &(&hedging_position.deposit_amount * additional_amount_to_cover) / &new_covered_amount;
+        let transaction_fees_percentage = self
+            .get_hedging_position_open_transaction_fees_percentage(&hedging_position.collateral_id);
+
+        self.calculate_percentage_of(&transaction_fees_percentage, &backing_deposit_amount)
+    }
+
+    fn set_position_close_orders(
+        &self,
+        nft_nonce: u64,
+        stop_loss_price: BigUint,
+        take_profit_price: BigUint,
+    ) -> SCResult<()> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        self.hedging_position(nft_nonce).update(|hedging_pos| {
+            self.require_not_closed(hedging_pos)?;
+            if stop_loss_price > 0 && take_profit_price > 0 {
+                require!(
+                    stop_loss_price < take_profit_price,
+                    "Stop loss must be lower than take profit"
+                );
+            }
+
+            hedging_pos.stop_loss_price = self.non_zero_or_none(stop_loss_price);
+            hedging_pos.take_profit_price = self.non_zero_or_none(take_profit_price);
+
+            Ok(())
+        })?;
+
+        // return the nft
+        let caller = self.blockchain().get_caller();
+        self.return_hedging_token(&caller, nft_nonce);
+
+        Ok(())
+    }
+
+    fn partial_close_position(
+        &self,
+        nft_nonce: u64,
+        amount_to_close: BigUint,
+        min_oracle_value: BigUint,
+    ) -> SCResult<()> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        self.require_not_closed(&hedging_position)?;
+        self.settle_funding(&mut hedging_position);
+        self.refresh_fee_configuration_if_stale(&hedging_position.collateral_id);
+        require!(
+            amount_to_close > 0 && amount_to_close < hedging_position.covered_amount,
+            "Invalid amount to close, use closeHedgingPosition for full closes"
+        );
+
+        let closed_part = self.split_hedging_position(&mut hedging_position, amount_to_close);
+        self.close_position(&closed_part)?;
+
+        let withdraw_amount_fee_split =
+            self.get_withdraw_amount_and_update_fees(&closed_part, Some(min_oracle_value))?;
+        let withdraw_split = self.calculate_withdraw_amounts_split(
+            &closed_part.collateral_id,
+            withdraw_amount_fee_split.withdraw_amount,
+        );
+
+        self.update_pool(&closed_part.collateral_id, |pool| {
+            pool.collateral_reserves -= &withdraw_split.collateral_amount;
+        });
+
+        let new_nft_nonce = self.refresh_hedging_token(nft_nonce, &hedging_position);
+
+        let caller = self.blockchain().get_caller();
+        self.send().direct(
+            &caller,
+            &closed_part.collateral_id,
+            0,
+            &withdraw_split.collateral_amount,
+            &[],
+        );
+
+        let liq_tokens_amount = withdraw_split.liq_tokens_amount;
+        if liq_tokens_amount > 0 {
+            self.create_and_send_liq_tokens(
+                &caller,
+                &closed_part.collateral_id,
+                &liq_tokens_amount,
+            );
+        }
+
+        self.return_hedging_token(&caller, new_nft_nonce);
+
+        self.emit_partial_close_hedging_position_event(
+            new_nft_nonce,
+            &closed_part.collateral_id,
+            &closed_part.covered_amount,
+            &withdraw_split.collateral_amount,
+            &liq_tokens_amount,
+            &withdraw_amount_fee_split.fees_amount,
+            &withdraw_amount_fee_split.collateral_value_in_dollars,
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
+
+        Ok(())
+    }
+
+    fn add_margin_to_position(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        amount: &BigUint,
+    ) -> SCResult<()> {
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        require!(
+            collateral_id == &hedging_position.collateral_id,
+            "Second token should be the collateral for the position"
+        );
+        self.require_not_closed(&hedging_position)?;
+        self.settle_funding(&mut hedging_position);
+
+        hedging_position.deposit_amount += amount;
+        self.require_under_max_leverage(&hedging_position)?;
+
+        self.update_pool(collateral_id, |pool| {
+            pool.collateral_reserves += amount;
+        });
+
+        let new_nft_nonce = self.refresh_hedging_token(nft_nonce, &hedging_position);
+
+        let caller = self.blockchain().get_caller();
+        self.return_hedging_token(&caller, new_nft_nonce);
+
+        self.emit_add_margin_event(
+            new_nft_nonce,
+            collateral_id,
+            amount,
+            &hedging_position.deposit_amount,
+        );
+
+        Ok(())
+    }
+
+    fn remove_margin_from_position(
+        &self,
+        nft_nonce: u64,
+        amount_to_remove: &BigUint,
+    ) -> SCResult<()> {
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        let collateral_id = hedging_position.collateral_id.clone();
+        self.settle_funding(&mut hedging_position);
+        require!(
+            amount_to_remove < &hedging_position.deposit_amount,
+            "Remove amount higher than total deposit"
+        );
+        self.require_not_closed(&hedging_position)?;
+
+        hedging_position.deposit_amount -= amount_to_remove;
+        self.require_under_max_leverage(&hedging_position)?;
+
+        self.update_pool(&collateral_id, |pool| {
+            require!(
+                amount_to_remove <= &pool.collateral_reserves,
+                "Not enough reserves in pool"
+            );
+
+            pool.collateral_reserves -= amount_to_remove;
+
+            Ok(())
+        })?;
+
+        let new_nft_nonce = self.refresh_hedging_token(nft_nonce, &hedging_position);
+
+        let caller = self.blockchain().get_caller();
+        self.send()
+            .direct(&caller, &collateral_id, 0, amount_to_remove, &[]);
+        self.return_hedging_token(&caller, new_nft_nonce);
+
+        self.emit_remove_margin_event(
+            new_nft_nonce,
+            &collateral_id,
+            amount_to_remove,
+            &hedging_position.deposit_amount,
+        );
+
+        Ok(())
+    }
+
+    // deduplicates code for close, partial close, force-close and liquidate
+    fn close_position(&self, hedging_position: &HedgingPosition<Self::Api>) -> SCResult<()> {
+        self.require_not_closed(hedging_position)?;
+        self.require_min_hedging_period_passed(hedging_position)?;
+
+        // subtract exactly what was added when the coverage was opened, so the total cannot underflow
+        let mut pool = self.get_pool(&hedging_position.collateral_id);
+        pool.total_covered_value_in_stablecoin -= &hedging_position.covered_value_in_stablecoin;
+        pool.total_collateral_covered -= &hedging_position.covered_amount;
+
+        self.set_pool(&hedging_position.collateral_id, &pool);
+
+        // nothing is covered anymore, so no position can make a profit off the lowest entry price
+        if pool.total_collateral_covered == 0 {
+            self.lowest_open_entry_price(&hedging_position.collateral_id)
+                .clear();
+        }
+
+        Ok(())
+    }
+
+    // funding owed since the position's last settlement, without modifying the storage
+    fn calculate_accrued_funding(&self, hedging_position: &HedgingPosition<Self::Api>) -> BigUint {
+        if hedging_position.is_closed() {
+            return BigUint::zero();
+        }
+
+        let funding_index = self.get_current_funding_index(&hedging_position.collateral_id);
+        let funding_index_diff = &funding_index - &hedging_position.funding_index;
+        self.calculate_funding_amount(&hedging_position.deposit_amount, &funding_index_diff)
+    }
+
+    // deducts the accrued funding from the deposit and pays it to the liquidity providers.
+    // The funding stays in the reserves, it only moves from the hedger's deposit to the liquidity providers' share
+    fn settle_funding(&self, hedging_position: &mut HedgingPosition<Self::Api>) {
+        if hedging_position.is_closed() {
+            return;
+        }
+
+        let funding_index = self.update_funding_index(&hedging_position.collateral_id);
+        let funding_index_diff = &funding_index - &hedging_position.funding_index;
+        let funding_amount =
+            self.calculate_funding_amount(&hedging_position.deposit_amount, &funding_index_diff);
+
+        hedging_position.deposit_amount -= &funding_amount;
+        hedging_position.funding_index = funding_index;
+        self.credit_funding_to_liq_providers(&hedging_position.collateral_id, &funding_amount);
+    }
+
+    // An upper bound of what the hedgers would withdraw if all the positions were closed at the given price,
+    // before the close fees, including the force-closed positions that were not withdrawn yet.
+    // It's taken from running totals, so the cost doesn't grow with the number of positions.
+    // A position's losses past its deposit can't be netted against the others' profits, so losses are not counted at all,
+    // and every position's profit is taken as if it was opened at the lowest entry price.
+    // The funding not settled yet is still counted in the deposits, where it's owed to the liquidity providers instead
+    fn calculate_hedgers_liability(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        let pool = self.get_pool(collateral_id);
+        let mut liability = self.pending_force_close_withdrawals(collateral_id).get()
+            + self.total_hedging_deposits(collateral_id).get();
+
+        // withdraw_amount = x + y * (1 - initial_oracle / current_oracle), and initial_oracle is at least the lowest one
+        let lowest_entry_price = self.lowest_open_entry_price(collateral_id).get();
+        let covered_value_at_lowest_price = self.get_collateral_value_in_stablecoin(
+            collateral_id,
+            &pool.total_collateral_covered,
+            &lowest_entry_price,
+        );
+        let covered_amount_at_current_price = self.get_stablecoin_value_in_collateral(
+            collateral_id,
+            &covered_value_at_lowest_price,
+            collateral_value_in_dollars,
+        );
+        if pool.total_collateral_covered > covered_amount_at_current_price {
+            liability += &(pool.total_collateral_covered - covered_amount_at_current_price);
+        }
+
+        liability
+    }
+
+    // The funding still owed is left out of the claim, as it belongs to the liquidity providers.
+    // Funding stops accruing once global settlement is triggered
+    fn calculate_hedging_position_claim(
+        &self,
+        mut hedging_position: HedgingPosition<Self::Api>,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        if let Some(withdraw_amount) = hedging_position.withdraw_amount_after_force_close {
+            return withdraw_amount;
+        }
+
+        let accrued_funding = self.calculate_accrued_funding(&hedging_position);
+        hedging_position.deposit_amount -= &accrued_funding;
+
+        self.calculate_base_withdraw_amount(&hedging_position, collateral_value_in_dollars)
+    }
+
+    // without any liquidity tokens created yet, there is no share to credit,
+    // so the funding is left in the reserves like the rest of the pool's gains
+    fn credit_funding_to_liq_providers(
+        &self,
+        collateral_id: &TokenIdentifier,
+        funding_amount: &BigUint,
+    ) {
+        if funding_amount == &0 {
+            return;
+        }
+
+        let sft_nonce = self.liq_sft_nonce_for_collateral(collateral_id).get();
+        if sft_nonce == 0 {
+            return;
+        }
+
+        self.collateral_amount_for_liq_token(sft_nonce)
+            .update(|collateral_amount| *collateral_amount += funding_amount);
+    }
+
+    fn get_withdraw_amount_and_update_fees(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        opt_min_oracle_value: Option<BigUint>,
+    ) -> SCResult<HedgerWithdrawAmountFeeSplit<Self::Api>> {
+        let withdraw_amount_fees_pair =
+            self.calculate_withdraw_and_fee_amount(hedging_position, opt_min_oracle_value)?;
+
+        self.accumulated_tx_fees(&hedging_position.collateral_id)
+            .update(|accumulated_fees| *accumulated_fees += &withdraw_amount_fees_pair.fees_amount);
+
+        Ok(withdraw_amount_fees_pair)
+    }
+
+    fn get_withdraw_amount_at_price_and_update_fees(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        collateral_value_in_dollars: BigUint,
+    ) -> HedgerWithdrawAmountFeeSplit<Self::Api> {
+        let withdraw_amount_fees_pair = self.calculate_withdraw_and_fee_amount_at_price(
+            hedging_position,
+            collateral_value_in_dollars,
+        );
+
+        self.accumulated_tx_fees(&hedging_position.collateral_id)
+            .update(|accumulated_fees| *accumulated_fees += &withdraw_amount_fees_pair.fees_amount);
+
+        withdraw_amount_fees_pair
+    }
+
+    fn calculate_withdraw_and_fee_amount(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        opt_min_oracle_value: Option<BigUint>,
+    ) -> SCResult<HedgerWithdrawAmountFeeSplit<Self::Api>> {
+        let collateral_value_in_dollars = self.get_collateral_price(
+            &hedging_position.collateral_id,
+            PricedOperation::HedgingClose,
+        )?;
+        if let Some(min_oracle_value) = opt_min_oracle_value {
+            require!(
+                collateral_value_in_dollars >= min_oracle_value,
+                "Oracle value is lower than the provided min"
+            );
+        }
+
+        Ok(self.calculate_withdraw_and_fee_amount_at_price(
+            hedging_position,
+            collateral_value_in_dollars,
+        ))
+    }
+
+    fn calculate_withdraw_and_fee_amount_at_price(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        collateral_value_in_dollars: BigUint,
+    ) -> HedgerWithdrawAmountFeeSplit<Self::Api> {
+        let base_withdraw_amount =
+            self.calculate_base_withdraw_amount(hedging_position, &collateral_value_in_dollars);
+
+        let transaction_fees_percentage = self
+            .get_hedging_position_close_transaction_fees_percentage(
+                &hedging_position.collateral_id,
+            );
+        let fees_amount =
+            self.calculate_percentage_of(&transaction_fees_percentage, &base_withdraw_amount);
+        let withdraw_amount = &base_withdraw_amount - &fees_amount;
+
+        HedgerWithdrawAmountFeeSplit {
+            withdraw_amount,
+            fees_amount,
+            collateral_value_in_dollars,
+        }
+    }
+
+    fn calculate_margin_ratio(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        // margin = x / y + (1 - initial_oracle / current_oracle)
+        // where x is deposit_amount and y is amount_to_cover
+        let amount_ratio = self.calculate_ratio(
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
+        let price_ratio = self.calculate_ratio(
+            &hedging_position.oracle_value_at_deposit_time,
+            collateral_value_in_dollars,
+        );
+
+        let one = BigUint::from(ONE);
+        if price_ratio <= one {
+            let diff = one - price_ratio;
+            amount_ratio + diff
+        } else {
+            let diff = price_ratio - one;
+
+            // positions with losses over the deposit have a margin ratio of 0
+            if diff < amount_ratio {
+                amount_ratio - diff
+            } else {
+                BigUint::zero()
+            }
+        }
+    }
+
+    // the position is liquidatable once x / y + 1 - initial_oracle / current_oracle <= maintenance,
+    // i.e. current_oracle <= initial_oracle / (x / y + 1 - maintenance)
+    fn calculate_liquidation_price(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+    ) -> Option<BigUint> {
+        let hedging_maintenance_ratio = self
+            .hedging_maintenance_ratio(&hedging_position.collateral_id)
+            .get();
+        let amount_ratio = self.calculate_ratio(
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
+        let one = BigUint::from(ONE);
+        let margin_at_entry = amount_ratio + &one;
+        if margin_at_entry <= hedging_maintenance_ratio {
+            return None;
+        }
+
+        let denominator = &margin_at_entry - &hedging_maintenance_ratio;
+        Some(self.divide(
+            &hedging_position.oracle_value_at_deposit_time,
+            &denominator,
+            &one,
+        ))
+    }
+
+    fn calculate_base_withdraw_amount(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        let price_ratio = self.calculate_ratio(
+            &hedging_position.oracle_value_at_deposit_time,
+            collateral_value_in_dollars,
+        );
+
+        // withdraw_amount = x + y * (1 - initial_oracle / current_oracle),
+        // where x is deposit_amount and y is amount_to_cover
+        let one = BigUint::from(ONE);
+        if price_ratio <= one {
+            let factor = &one - &price_ratio;
+            let extra_amount = self.multiply(&factor, &hedging_position.covered_amount, &one);
+
+            &hedging_position.deposit_amount + &extra_amount
+        } else {
+            let factor = &price_ratio - &one;
+            let deducted_amount = self.multiply(&factor, &hedging_position.covered_amount, &one);
+
+            // losses are capped at the deposit
+            if deducted_amount < hedging_position.deposit_amount {
+                &hedging_position.deposit_amount - &deducted_amount
+            } else {
+                BigUint::zero()
+            }
+        }
+    }
+
+    fn calculate_withdraw_amounts_split(
+        &self,
+        collateral_id: &TokenIdentifier,
+        full_withdraw_amount: BigUint,
+    ) -> HedgerRewardAmountsTokensPair<Self::Api> {
+        let reserves = self.get_pool_reserves(collateral_id);
+        if full_withdraw_amount <= reserves {
+            HedgerRewardAmountsTokensPair {
+                collateral_amount: full_withdraw_amount,
+                liq_tokens_amount: BigUint::zero(),
+            }
+        } else {
+            let collateral_amount_over_reserves = &full_withdraw_amount - &reserves;
+            let collateral_precision = self.get_collateral_precision(collateral_id);
+            let amount_in_liq_tokens = self.collateral_to_liq_tokens(
+                collateral_id,
+                &collateral_amount_over_reserves,
+                &collateral_precision,
+            );
+
+            HedgerRewardAmountsTokensPair {
+                collateral_amount: reserves,
+                liq_tokens_amount: amount_in_liq_tokens,
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn calculate_leverage(
+        &self,
+        collateral_amount: &BigUint,
+        amount_to_cover: &BigUint,
+    ) -> BigUint {
+        // x + y / x
+        self.calculate_ratio(&(collateral_amount + amount_to_cover), collateral_amount)
+    }
+
+    fn require_under_max_leverage(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+    ) -> SCResult<()> {
+        let max_leverage = self.max_leverage(&hedging_position.collateral_id).get();
+        let position_leverage = self.calculate_leverage(
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
+        require!(position_leverage <= max_leverage, "Leverage too high");
+
+        Ok(())
+    }
+
+    fn get_hedging_token_attributes(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+    ) -> HedgingTokenAttributes<Self::Api> {
+        HedgingTokenAttributes {
+            collateral_id: hedging_position.collateral_id.clone(),
+            deposit_amount: hedging_position.deposit_amount.clone(),
+            covered_amount: hedging_position.covered_amount.clone(),
+            oracle_value_at_deposit_time: hedging_position.oracle_value_at_deposit_time.clone(),
+            creation_timestamp: hedging_position.creation_timestamp,
+        }
+    }
+
+    // Moves amount_to_close of the coverage out of the position, and returns it as a separate position.
+    // The deposit and covered value are reduced in the same proportion as the covered amount
+    fn split_hedging_position(
+        &self,
+        hedging_position: &mut HedgingPosition<Self::Api>,
+        amount_to_close: BigUint,
+    ) -> HedgingPosition<Self::Api> {
+        let closed_deposit_amount = &(&hedging_position.deposit_amount * &amount_to_close)
+            / &hedging_position.covered_amount;
+        let closed_covered_value = &(&hedging_position.covered_value_in_stablecoin
+            * &amount_to_close)
+            / &hedging_position.covered_amount;
+
+        hedging_position.deposit_amount -= &closed_deposit_amount;
+        hedging_position.covered_amount -= &amount_to_close;
+        hedging_position.covered_value_in_stablecoin -= &closed_covered_value;
+
+        HedgingPosition {
+            collateral_id: hedging_position.collateral_id.clone(),
+            deposit_amount: closed_deposit_amount,
+            covered_amount: amount_to_close,
+            oracle_value_at_deposit_time: hedging_position.oracle_value_at_deposit_time.clone(),
+            covered_value_in_stablecoin: closed_covered_value,
+            creation_timestamp: hedging_position.creation_timestamp,
+            withdraw_amount_after_force_close: None,
+            stop_loss_price: None,
+            take_profit_price: None,
+            funding_index: hedging_position.funding_index.clone(),
+        }
+    }
+
+    // Every write that may change a deposit goes through here, to keep the open positions' total in sync.
+    // Force-closed positions are counted in the pending withdrawals instead
+    fn set_hedging_position(&self, nft_nonce: u64, hedging_position: &HedgingPosition<Self::Api>) {
+        self.clear_hedging_position(nft_nonce);
+        if !hedging_position.is_closed() {
+            self.total_hedging_deposits(&hedging_position.collateral_id)
+                .update(|total| *total += &hedging_position.deposit_amount);
+            self.update_lowest_open_entry_price(hedging_position);
+        }
+        self.hedging_position(nft_nonce).set(hedging_position);
+    }
+
+    // Only ever lowered while any collateral is covered, which keeps it a lower bound of the open positions' entry prices.
+    // Positions that cover nothing can't make a profit, so they don't need to be counted
+    fn update_lowest_open_entry_price(&self, hedging_position: &HedgingPosition<Self::Api>) {
+        if hedging_position.covered_amount == 0 {
+            return;
+        }
+
+        let mapper = self.lowest_open_entry_price(&hedging_position.collateral_id);
+        if mapper.is_empty() || hedging_position.oracle_value_at_deposit_time < mapper.get() {
+            mapper.set(&hedging_position.oracle_value_at_deposit_time);
+        }
+    }
+
+    fn clear_hedging_position(&self, nft_nonce: u64) {
+        let mapper = self.hedging_position(nft_nonce);
+        if mapper.is_empty() {
+            return;
+        }
+
+        let hedging_position = mapper.get();
+        if !hedging_position.is_closed() {
+            self.total_hedging_deposits(&hedging_position.collateral_id)
+                .update(|total| *total -= &hedging_position.deposit_amount);
+        }
+        mapper.clear();
+    }
+
+    // NFT attributes can't be updated, so the old NFT is burned
+    // and the position is moved to a newly created one
+    fn refresh_hedging_token(
+        &self,
+        old_nft_nonce: u64,
+        hedging_position: &HedgingPosition<Self::Api>,
+    ) -> u64 {
+        self.burn_hedging_token(old_nft_nonce);
+        let new_nft_nonce =
+            self.create_hedging_token(&self.get_hedging_token_attributes(hedging_position));
+
+        self.clear_hedging_position(old_nft_nonce);
+        self.set_hedging_position(new_nft_nonce, hedging_position);
+
+        // a deposited NFT may be refreshed by a keeper, so its owner is kept instead of the caller
+        let deposited_owner_mapper = self.deposited_hedging_token_owner(old_nft_nonce);
+        let owner = if !deposited_owner_mapper.is_empty() {
+            let deposited_owner = deposited_owner_mapper.get();
+            self.deposited_hedging_token_owner(new_nft_nonce)
+                .set(&deposited_owner);
+            deposited_owner_mapper.clear();
+            deposited_owner
+        } else {
+            self.blockchain().get_caller()
+        };
+
+        self.remove_from_position_indexes(old_nft_nonce, &hedging_position.collateral_id);
+        self.add_to_position_indexes(new_nft_nonce, &hedging_position.collateral_id, &owner);
+
+        new_nft_nonce
+    }
+
+    // The contract can't see transfers between accounts, so the owner of a position
+    // is the last address that received the position's NFT from the contract
+    fn add_to_position_indexes(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        owner: &ManagedAddress,
+    ) {
+        self.open_position_nonces(collateral_id).insert(nft_nonce);
+        self.owner_position_nonces(owner).insert(nft_nonce);
+        self.position_owner(nft_nonce).set(owner);
+    }
+
+    // the withdraw amount stays in the reserves until the owner claims it
+    fn set_position_force_closed(
+        &self,
+        nft_nonce: u64,
+        hedging_position: &mut HedgingPosition<Self::Api>,
+        withdraw_amount: BigUint,
+    ) {
+        self.pending_force_close_withdrawals(&hedging_position.collateral_id)
+            .update(|pending| *pending += &withdraw_amount);
+        hedging_position.withdraw_amount_after_force_close = Some(withdraw_amount);
+        self.set_hedging_position(nft_nonce, hedging_position);
+        self.remove_from_open_positions(nft_nonce, &hedging_position.collateral_id);
+    }
+
+    // force-closed positions are no longer open, but they still belong to their owner until withdrawn
+    fn remove_from_open_positions(&self, nft_nonce: u64, collateral_id: &TokenIdentifier) {
+        self.open_position_nonces(collateral_id).remove(&nft_nonce);
+    }
+
+    // force-closed positions stay out of the open positions, only the owner entry is moved
+    fn move_position_owner(&self, nft_nonce: u64, new_owner: &ManagedAddress) {
+        let old_owner = self.position_owner(nft_nonce).get();
+        self.owner_position_nonces(&old_owner).remove(&nft_nonce);
+        self.owner_position_nonces(new_owner).insert(nft_nonce);
+        self.position_owner(nft_nonce).set(new_owner);
+    }
+
+    fn remove_from_position_indexes(&self, nft_nonce: u64, collateral_id: &TokenIdentifier) {
+        self.remove_from_open_positions(nft_nonce, collateral_id);
+
+        let owner = self.position_owner(nft_nonce).get();
+        self.owner_position_nonces(&owner).remove(&nft_nonce);
+        self.position_owner(nft_nonce).clear();
+        self.hedging_token_attributes_stale(nft_nonce).clear();
+    }
+
+    // deposited NFTs are kept in the contract
+    fn return_hedging_token(&self, to: &ManagedAddress, nft_nonce: u64) {
+        if self.deposited_hedging_token_owner(nft_nonce).is_empty() {
+            self.send_hedging_token(to, nft_nonce);
+        }
+    }
+
+    // the position is gone, so there is nothing left to withdraw the NFT for
+    fn burn_deposited_hedging_token(&self, nft_nonce: u64) {
+        let deposited_owner_mapper = self.deposited_hedging_token_owner(nft_nonce);
+        if !deposited_owner_mapper.is_empty() {
+            deposited_owner_mapper.clear();
+            self.burn_hedging_token(nft_nonce);
+        }
+    }
+
+    fn require_deposited_hedging_token_owner(&self, nft_nonce: u64) -> SCResult<ManagedAddress> {
+        let deposited_owner_mapper = self.deposited_hedging_token_owner(nft_nonce);
+        require!(
+            !deposited_owner_mapper.is_empty(),
+            "Hedging NFT is not deposited"
+        );
+
+        let caller = self.blockchain().get_caller();
+        require!(
+            deposited_owner_mapper.get() == caller,
+            "Only the depositor may manage the position"
+        );
+
+        Ok(caller)
+    }
+
+    fn non_zero_or_none(&self, value: BigUint) -> Option<BigUint> {
+        if value > 0 {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn require_min_hedging_period_passed(
+        &self,
+        hedging_position: &HedgingPosition<Self::Api>,
+    ) -> SCResult<()> {
+        let current_time = self.blockchain().get_block_timestamp();
+        let time_diff = current_time - hedging_position.creation_timestamp;
+        let min_hedging_period_seconds = self.min_hedging_period_seconds().get();
+        require!(
+            time_diff >= min_hedging_period_seconds,
+            "Trying to close too early"
+        );
+
+        Ok(())
+    }
+
+    fn require_not_closed(&self, hedging_position: &HedgingPosition<Self::Api>) -> SCResult<()> {
+        require!(!hedging_position.is_closed(), "Position already closed");
         Ok(())
     }
 
@@ -425,14 +1478,80 @@ pub trait HedgingAgentsModule:
     #[storage_mapper("hedgingPosition")]
     fn hedging_position(&self, nft_nonce: u64) -> SingleValueMapper<HedgingPosition<Self::Api>>;
 
+    #[storage_mapper("openPositionNonces")]
+    fn open_position_nonces(&self, collateral_id: &TokenIdentifier) -> SetMapper<u64>;
+
+    // empty while nothing is covered
+    #[storage_mapper("lowestOpenEntryPrice")]
+    fn lowest_open_entry_price(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[storage_mapper("ownerPositionNonces")]
+    fn owner_position_nonces(&self, owner: &ManagedAddress) -> SetMapper<u64>;
+
+    #[view(getPositionOwner)]
+    #[storage_mapper("positionOwner")]
+    fn position_owner(&self, nft_nonce: u64) -> SingleValueMapper<ManagedAddress>;
+
+    #[view(getDepositedHedgingTokenOwner)]
+    #[storage_mapper("depositedHedgingTokenOwner")]
+    fn deposited_hedging_token_owner(&self, nft_nonce: u64) -> SingleValueMapper<ManagedAddress>;
+
+    // set when the position changed while its NFT was held outside the contract,
+    // until the NFT is refreshed. The stored position is the one to trust
+    #[view(areHedgingTokenAttributesStale)]
+    #[storage_mapper("hedgingTokenAttributesStale")]
+    fn hedging_token_attributes_stale(&self, nft_nonce: u64) -> SingleValueMapper<bool>;
+
+    #[view(getTotalHedgingDeposits)]
+    #[storage_mapper("totalHedgingDeposits")]
+    fn total_hedging_deposits(&self, collateral_id: &TokenIdentifier)
+        -> SingleValueMapper<BigUint>;
+
     #[view(getMinHedgingPeriodSeconds)]
     #[storage_mapper("minHedgingPeriodSeconds")]
     fn min_hedging_period_seconds(&self) -> SingleValueMapper<u64>;
 
+    #[view(getPendingForceCloseWithdrawals)]
+    #[storage_mapper("pendingForceCloseWithdrawals")]
+    fn pending_force_close_withdrawals(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    // 0 means positions never expire
+    #[view(getMaxHedgingPeriodSeconds)]
+    #[storage_mapper("maxHedgingPeriodSeconds")]
+    fn max_hedging_period_seconds(&self, collateral_id: &TokenIdentifier)
+        -> SingleValueMapper<u64>;
+
     #[view(getMaxLeverage)]
     #[storage_mapper("maxLeverage")]
     fn max_leverage(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<BigUint>;
 
+    #[view(getCloseOrderBountyPercentage)]
+    #[storage_mapper("closeOrderBountyPercentage")]
+    fn close_order_bounty_percentage(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[view(getForceCloseOrdering)]
+    #[storage_mapper("forceCloseOrdering")]
+    fn force_close_ordering(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<ForceCloseOrdering>;
+
+    #[view(getLiquidationBountyPercentage)]
+    #[storage_mapper("liquidationBountyPercentage")]
+    fn liquidation_bounty_percentage(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
     #[view(getHedgingMaintenanceRatio)]
     #[storage_mapper("hedgingMaintenanceRatio")]
     fn hedging_maintenance_ratio(
@@ -1,40 +1,79 @@
 elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::{
+    hedging_agents::{ForceCloseOrdering, HedgingPosition},
+    pause::PausableAction,
+    twap::PricedOperation,
+};
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum KeeperAction {
+    RebalancePool,
+    UpdateFeesPercentage,
+    SplitFees,
+}
+
+impl KeeperAction {
+    pub fn from_index(index: u64) -> Option<Self> {
+        match index {
+            0 => Some(KeeperAction::RebalancePool),
+            1 => Some(KeeperAction::UpdateFeesPercentage),
+            2 => Some(KeeperAction::SplitFees),
+            _ => None,
+        }
+    }
 
-use crate::{fees::CurrentFeeConfiguration, hedging_agents::HedgingPosition, math::ONE};
+    pub fn all() -> [Self; 3] {
+        [
+            KeeperAction::RebalancePool,
+            KeeperAction::UpdateFeesPercentage,
+            KeeperAction::SplitFees,
+        ]
+    }
+}
 
 #[elrond_wasm::module]
 pub trait KeepersModule:
-    crate::fees::FeesModule
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
     + crate::hedging_agents::HedgingAgentsModule
     + crate::hedging_token::HedgingTokenModule
     + crate::liquidity_providers::LiquidityProvidersModule
     + crate::liquidity_token::LiquidityTokenModule
     + crate::math::MathModule
+    + crate::pause::PauseModule
     + crate::pools::PoolsModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
     + crate::token_common::TokenCommonModule
+    + crate::treasury::TreasuryModule
+    + crate::twap::TwapModule
 {
     #[endpoint(rebalancePool)]
     fn rebalance_pool(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
         self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
 
-        let collateral_value_in_dollars = self.get_collateral_value_in_dollars(&collateral_id)?;
-        let collateral_precision = self.get_collateral_precision(&collateral_id);
+        let collateral_value_in_dollars = self.get_spot_price_and_update_twap(&collateral_id)?;
+        let old_stablecoin_amount = self.get_pool(&collateral_id).stablecoin_amount;
 
         self.update_pool(&collateral_id, |pool| {
-            let pool_value_in_dollars = self.multiply(
+            let pool_value_in_dollars = self.get_collateral_value_in_stablecoin(
+                &collateral_id,
                 &pool.collateral_amount,
                 &collateral_value_in_dollars,
-                &collateral_precision,
             );
 
             // collateral value increased, so we move the extra to reserves
             if pool_value_in_dollars > pool.stablecoin_amount {
                 let extra_collateral_in_dollars = &pool_value_in_dollars - &pool.stablecoin_amount;
-                let extra_collateral_amount = self.divide(
+                let extra_collateral_amount = self.get_stablecoin_value_in_collateral(
+                    &collateral_id,
                     &extra_collateral_in_dollars,
                     &collateral_value_in_dollars,
-                    &collateral_precision,
                 );
 
                 pool.collateral_reserves += extra_collateral_amount;
@@ -43,10 +82,10 @@ pub trait KeepersModule:
             else {
                 let missing_collateral_in_dollars =
                     &pool.stablecoin_amount - &pool_value_in_dollars;
-                let missing_collateral_amount = self.divide(
+                let missing_collateral_amount = self.get_stablecoin_value_in_collateral(
+                    &collateral_id,
                     &missing_collateral_in_dollars,
                     &collateral_value_in_dollars,
-                    &collateral_precision,
                 );
 
                 require!(
@@ -60,25 +99,36 @@ pub trait KeepersModule:
             pool.stablecoin_amount = pool_value_in_dollars;
 
             Ok(())
-        })
+        })?;
+
+        self.emit_rebalance_pool_event(
+            &collateral_id,
+            &collateral_value_in_dollars,
+            &old_stablecoin_amount,
+        );
+        self.pay_keeper_reward(&collateral_id, KeeperAction::RebalancePool);
+
+        Ok(())
     }
 
     #[endpoint(updateFeesPercentage)]
-    fn update_fees_percentage(&self, collateral_id: TokenIdentifier) {
-        let hedging_ratio = self.calculate_current_hedging_ratio(&collateral_id);
-        let mint_fee_percentage = self.calculate_mint_transaction_fees_percentage(&collateral_id);
-        let burn_fee_percentage = self.calculate_burn_transaction_fees_percentage(&collateral_id);
-
-        self.current_fee_configuration(&collateral_id)
-            .set(&CurrentFeeConfiguration {
-                hedging_ratio,
-                mint_fee_percentage,
-                burn_fee_percentage,
-            });
+    fn update_fees_percentage(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        self.update_fee_configuration(&collateral_id);
+        self.pay_keeper_reward(&collateral_id, KeeperAction::UpdateFeesPercentage);
+
+        Ok(())
     }
 
     #[endpoint(splitFees)]
     fn split_fees(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        // the reward is taken out of the fees before they are split
+        self.pay_keeper_reward(&collateral_id, KeeperAction::SplitFees);
+
         let liq_provider_fee_reward_percentage = self
             .liq_provider_fee_reward_percentage(&collateral_id)
             .get();
@@ -88,36 +138,182 @@ pub trait KeepersModule:
             self.calculate_percentage_of(&liq_provider_fee_reward_percentage, &accumulated_fees);
         let leftover = &accumulated_fees - &liq_provider_reward;
 
+        // the treasury gets its share out of what would otherwise go to the reserves
+        let treasury_fee_percentage = self.treasury_fee_percentage(&collateral_id).get();
+        let treasury_amount = self.calculate_percentage_of(&treasury_fee_percentage, &leftover);
+        let reserves_amount = &leftover - &treasury_amount;
+
         let sft_nonce = self.liq_sft_nonce_for_collateral(&collateral_id).get();
         self.collateral_amount_for_liq_token(sft_nonce)
-            .update(|amt| *amt += liq_provider_reward);
+            .update(|amt| *amt += &liq_provider_reward);
         self.update_pool(&collateral_id, |pool| {
-            pool.collateral_reserves += leftover;
+            pool.collateral_reserves += &reserves_amount;
         });
 
+        self.treasury_balance(&collateral_id)
+            .update(|balance| *balance += &treasury_amount);
         self.accumulated_tx_fees(&collateral_id).clear();
 
+        self.emit_split_fees_event(
+            &collateral_id,
+            &accumulated_fees,
+            &liq_provider_reward,
+            &treasury_amount,
+            &reserves_amount,
+        );
+
         Ok(())
     }
 
-    #[endpoint(forceCloseHedgingPosition)]
-    fn force_close_hedging_position(&self, nft_nonce: u64) -> SCResult<()> {
+    // Positions are only closed in the order returned by getForceCloseCandidates, and only until the pool is back at the limit.
+    // Other positions are skipped instead of failing the whole batch.
+    // The last position is only partially closed if closing it whole would bring the pool under the limit
+    #[endpoint(forceCloseHedgingPositions)]
+    fn force_close_hedging_positions(
+        &self,
+        collateral_id: TokenIdentifier,
+        #[var_args] nft_nonces: VarArgs<u64>,
+    ) -> SCResult<MultiResultVec<MultiResult2<u64, bool>>> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+        require!(
+            self.is_over_hedge_limit(&collateral_id),
+            "May only force close after limit hedge amount is passed"
+        );
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::HedgingClose)?;
+
+        let results = self.force_close_until_limit(
+            &collateral_id,
+            nft_nonces.into_vec(),
+            &collateral_value_in_dollars,
+        );
+
+        Ok(results
+            .into_iter()
+            .map(|(nft_nonce, is_force_closed)| MultiResult2::from((nft_nonce, is_force_closed)))
+            .collect())
+    }
+
+    // loads every open position of the collateral, so its gas cost grows with the number of open positions
+    #[view(getForceCloseCandidates)]
+    fn get_force_close_candidates_view(
+        &self,
+        collateral_id: TokenIdentifier,
+    ) -> MultiResultVec<u64> {
+        self.get_force_close_candidates(&collateral_id)
+            .into_iter()
+            .collect()
+    }
+
+    // the position is closed at the current price the same way as a force-close,
+    // and the owner withdraws the proceeds with the NFT through closeHedgingPosition
+    #[endpoint(expireHedgingPosition)]
+    fn expire_hedging_position(&self, nft_nonce: u64) -> SCResult<()> {
         self.require_not_liquidated(nft_nonce)?;
 
         let mut hedging_position = self.hedging_position(nft_nonce).get();
-        let pool = self.get_pool(&hedging_position.collateral_id);
+        let collateral_id = hedging_position.collateral_id.clone();
+        self.require_not_closed(&hedging_position)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
 
-        let limit_hedge_amount = self.calculate_limit_hedge_amount(&pool.collateral_amount);
+        let max_hedging_period_seconds = self.max_hedging_period_seconds(&collateral_id).get();
+        let current_time = self.blockchain().get_block_timestamp();
         require!(
-            pool.total_covered_value_in_stablecoin > limit_hedge_amount,
-            "May only force close after limit hedge amount is passed"
+            max_hedging_period_seconds > 0
+                && current_time - hedging_position.creation_timestamp > max_hedging_period_seconds,
+            "Position has not expired"
         );
 
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::HedgingClose)?;
+
+        self.settle_funding(&mut hedging_position);
         self.close_position(&hedging_position)?;
 
-        let withdraw_amount = self.get_withdraw_amount_and_update_fees(&hedging_position, None)?;
-        hedging_position.withdraw_amount_after_force_close = Some(withdraw_amount);
-        self.hedging_position(nft_nonce).set(&hedging_position);
+        let withdraw_amount_fee_split = self.get_withdraw_amount_at_price_and_update_fees(
+            &hedging_position,
+            collateral_value_in_dollars,
+        );
+        self.set_position_force_closed(
+            nft_nonce,
+            &mut hedging_position,
+            withdraw_amount_fee_split.withdraw_amount.clone(),
+        );
+
+        self.emit_expire_hedging_position_event(
+            nft_nonce,
+            &collateral_id,
+            &withdraw_amount_fee_split.withdraw_amount,
+            &withdraw_amount_fee_split.fees_amount,
+            &withdraw_amount_fee_split.collateral_value_in_dollars,
+        );
+
+        Ok(())
+    }
+
+    // the position is closed the same way as a force-close,
+    // and the caller receives a bounty out of the position's withdraw amount
+    #[endpoint(executeCloseOrder)]
+    fn execute_close_order(&self, nft_nonce: u64) -> SCResult<()> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        let collateral_id = hedging_position.collateral_id.clone();
+        self.require_not_closed(&hedging_position)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::HedgingClose)?;
+        let stop_loss_triggered = match &hedging_position.stop_loss_price {
+            Some(stop_loss_price) => &collateral_value_in_dollars <= stop_loss_price,
+            None => false,
+        };
+        let take_profit_triggered = match &hedging_position.take_profit_price {
+            Some(take_profit_price) => &collateral_value_in_dollars >= take_profit_price,
+            None => false,
+        };
+        require!(
+            stop_loss_triggered || take_profit_triggered,
+            "No close order triggered at the current price"
+        );
+
+        self.settle_funding(&mut hedging_position);
+        self.close_position(&hedging_position)?;
+
+        let withdraw_amount_fee_split = self.get_withdraw_amount_at_price_and_update_fees(
+            &hedging_position,
+            collateral_value_in_dollars.clone(),
+        );
+        let bounty_percentage = self.close_order_bounty_percentage(&collateral_id).get();
+        let bounty_amount = self.calculate_bounty_amount(
+            &collateral_id,
+            &bounty_percentage,
+            &withdraw_amount_fee_split.withdraw_amount,
+        );
+
+        let withdraw_amount = &withdraw_amount_fee_split.withdraw_amount - &bounty_amount;
+        self.set_position_force_closed(nft_nonce, &mut hedging_position, withdraw_amount.clone());
+
+        if bounty_amount > 0 {
+            self.update_pool(&collateral_id, |pool| {
+                pool.collateral_reserves -= &bounty_amount;
+            });
+
+            let caller = self.blockchain().get_caller();
+            self.send()
+                .direct(&caller, &collateral_id, 0, &bounty_amount, &[]);
+        }
+
+        self.emit_execute_close_order_event(
+            nft_nonce,
+            &collateral_id,
+            &withdraw_amount,
+            &withdraw_amount_fee_split.fees_amount,
+            &bounty_amount,
+            &collateral_value_in_dollars,
+        );
 
         Ok(())
     }
@@ -126,10 +322,179 @@ pub trait KeepersModule:
     fn liquidate_hedging_position(&self, nft_nonce: u64) -> SCResult<()> {
         self.require_not_liquidated(nft_nonce)?;
 
-        let hedging_position = self.hedging_position(nft_nonce).get();
+        let collateral_id = self.hedging_position(nft_nonce).get().collateral_id;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::Liquidation)?;
+
+        self.liquidate_position(nft_nonce, &collateral_value_in_dollars)
+    }
+
+    // ineligible positions, or positions of other collaterals, are skipped instead of failing the whole batch
+    #[endpoint(batchLiquidateHedgingPositions)]
+    fn batch_liquidate_hedging_positions(
+        &self,
+        collateral_id: TokenIdentifier,
+        #[var_args] nft_nonces: VarArgs<u64>,
+    ) -> SCResult<MultiResultVec<MultiResult2<u64, bool>>> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::Liquidation)?;
+
+        let results = self.liquidate_positions(
+            &collateral_id,
+            nft_nonces.into_vec(),
+            &collateral_value_in_dollars,
+        );
+
+        Ok(results
+            .into_iter()
+            .map(|(nft_nonce, is_liquidated)| MultiResult2::from((nft_nonce, is_liquidated)))
+            .collect())
+    }
+
+    // private
+
+    fn force_close_position(
+        &self,
+        nft_nonce: u64,
+        collateral_value_in_dollars: &BigUint,
+    ) -> SCResult<()> {
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
+        let pool = self.get_pool(&hedging_position.collateral_id);
+
+        let limit_hedge_amount = self.calculate_limit_hedge_amount(&pool.collateral_amount);
+        require!(
+            pool.total_collateral_covered > limit_hedge_amount,
+            "May only force close after limit hedge amount is passed"
+        );
+
+        self.settle_funding(&mut hedging_position);
+
+        let amount_over_limit = &pool.total_collateral_covered - &limit_hedge_amount;
+        if hedging_position.covered_amount > amount_over_limit {
+            return self.partial_force_close_position(
+                nft_nonce,
+                hedging_position,
+                amount_over_limit,
+                collateral_value_in_dollars,
+            );
+        }
+
+        self.close_position(&hedging_position)?;
+
+        let withdraw_amount_fee_split = self.get_withdraw_amount_at_price_and_update_fees(
+            &hedging_position,
+            collateral_value_in_dollars.clone(),
+        );
+        self.set_position_force_closed(
+            nft_nonce,
+            &mut hedging_position,
+            withdraw_amount_fee_split.withdraw_amount.clone(),
+        );
+
+        self.emit_force_close_hedging_position_event(
+            nft_nonce,
+            &hedging_position.collateral_id,
+            &withdraw_amount_fee_split.withdraw_amount,
+            &withdraw_amount_fee_split.fees_amount,
+            &withdraw_amount_fee_split.collateral_value_in_dollars,
+        );
+
+        Ok(())
+    }
+
+    // A position is only eligible if it's the next force-close candidate, so the caller can't pick
+    // which positions get closed. Duplicates and already closed positions are skipped as well
+    fn force_close_until_limit(
+        &self,
+        collateral_id: &TokenIdentifier,
+        nft_nonces: Vec<u64>,
+        collateral_value_in_dollars: &BigUint,
+    ) -> Vec<(u64, bool)> {
+        let candidates = self.get_force_close_candidates(collateral_id);
+        let mut next_candidate_index = 0;
+
+        nft_nonces
+            .into_iter()
+            .map(|nft_nonce| {
+                let is_force_closed = candidates.get(next_candidate_index) == Some(&nft_nonce)
+                    && self.is_over_hedge_limit(collateral_id)
+                    && self
+                        .force_close_position(nft_nonce, collateral_value_in_dollars)
+                        .is_ok();
+                if is_force_closed {
+                    next_candidate_index += 1;
+                }
+
+                (nft_nonce, is_force_closed)
+            })
+            .collect()
+    }
+
+    // Only the part over the limit is closed. The position stays open, so its withdraw amount
+    // is added to the remaining deposit instead of being set aside for the owner.
+    // A deposited NFT is refreshed right away, otherwise its attributes are marked as stale until the next refresh
+    fn partial_force_close_position(
+        &self,
+        nft_nonce: u64,
+        mut hedging_position: HedgingPosition<Self::Api>,
+        amount_to_close: BigUint,
+        collateral_value_in_dollars: &BigUint,
+    ) -> SCResult<()> {
+        let closed_part = self.split_hedging_position(&mut hedging_position, amount_to_close);
+        self.close_position(&closed_part)?;
+
+        let withdraw_amount_fee_split = self.get_withdraw_amount_at_price_and_update_fees(
+            &closed_part,
+            collateral_value_in_dollars.clone(),
+        );
+        hedging_position.deposit_amount += &withdraw_amount_fee_split.withdraw_amount;
+
+        let new_nft_nonce = if self.deposited_hedging_token_owner(nft_nonce).is_empty() {
+            self.set_hedging_position(nft_nonce, &hedging_position);
+            self.hedging_token_attributes_stale(nft_nonce).set(&true);
+            nft_nonce
+        } else {
+            self.refresh_hedging_token(nft_nonce, &hedging_position)
+        };
+
+        self.emit_partial_force_close_hedging_position_event(
+            new_nft_nonce,
+            &hedging_position.collateral_id,
+            &closed_part.covered_amount,
+            &withdraw_amount_fee_split.withdraw_amount,
+            &withdraw_amount_fee_split.fees_amount,
+            &withdraw_amount_fee_split.collateral_value_in_dollars,
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+        );
+
+        Ok(())
+    }
+
+    // the caller receives a bounty out of the position's remaining deposit,
+    // and the rest of it stays in the pool's reserves.
+    // All the checks are done before any storage is modified,
+    // so an error leaves the state untouched and batches may skip the position
+    fn liquidate_position(
+        &self,
+        nft_nonce: u64,
+        collateral_value_in_dollars: &BigUint,
+    ) -> SCResult<()> {
+        let mut hedging_position = self.hedging_position(nft_nonce).get();
         self.require_not_closed(&hedging_position)?;
+        self.require_min_hedging_period_passed(&hedging_position)?;
+
+        // the funding is only paid after the checks, to not modify the storage for skipped positions
+        let accrued_funding = self.calculate_accrued_funding(&hedging_position);
+        hedging_position.deposit_amount -= &accrued_funding;
 
-        let margin_ratio = self.calculate_margin_ratio(&hedging_position)?;
+        let margin_ratio =
+            self.calculate_margin_ratio(&hedging_position, collateral_value_in_dollars);
         let hedging_maintenance_ratio = self
             .hedging_maintenance_ratio(&hedging_position.collateral_id)
             .get();
@@ -138,39 +503,221 @@ pub trait KeepersModule:
             "Can only liquidate if margin ratio is below expected amount"
         );
 
+        self.update_funding_index(&hedging_position.collateral_id);
+        self.credit_funding_to_liq_providers(&hedging_position.collateral_id, &accrued_funding);
         self.close_position(&hedging_position)?;
-        self.hedging_position(nft_nonce).clear();
+        self.clear_hedging_position(nft_nonce);
+        self.remove_from_position_indexes(nft_nonce, &hedging_position.collateral_id);
+        self.burn_deposited_hedging_token(nft_nonce);
+
+        let remaining_deposit_amount =
+            self.calculate_base_withdraw_amount(&hedging_position, collateral_value_in_dollars);
+        let bounty_percentage = self
+            .liquidation_bounty_percentage(&hedging_position.collateral_id)
+            .get();
+        let bounty_amount = self.calculate_bounty_amount(
+            &hedging_position.collateral_id,
+            &bounty_percentage,
+            &remaining_deposit_amount,
+        );
+        let reserves_amount = if bounty_amount < remaining_deposit_amount {
+            &remaining_deposit_amount - &bounty_amount
+        } else {
+            BigUint::zero()
+        };
+
+        if bounty_amount > 0 {
+            self.update_pool(&hedging_position.collateral_id, |pool| {
+                pool.collateral_reserves -= &bounty_amount;
+            });
+
+            let caller = self.blockchain().get_caller();
+            self.send().direct(
+                &caller,
+                &hedging_position.collateral_id,
+                0,
+                &bounty_amount,
+                &[],
+            );
+        }
+
+        self.emit_liquidate_hedging_position_event(
+            nft_nonce,
+            &hedging_position.collateral_id,
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount,
+            &margin_ratio,
+            collateral_value_in_dollars,
+            &bounty_amount,
+            &reserves_amount,
+        );
 
         Ok(())
     }
 
-    fn calculate_margin_ratio(
+    fn liquidate_positions(
+        &self,
+        collateral_id: &TokenIdentifier,
+        nft_nonces: Vec<u64>,
+        collateral_value_in_dollars: &BigUint,
+    ) -> Vec<(u64, bool)> {
+        nft_nonces
+            .into_iter()
+            .map(|nft_nonce| {
+                let is_liquidated = self.is_position_of_collateral(nft_nonce, collateral_id)
+                    && self
+                        .liquidate_position(nft_nonce, collateral_value_in_dollars)
+                        .is_ok();
+
+                (nft_nonce, is_liquidated)
+            })
+            .collect()
+    }
+
+    // open positions past the min hedging period, in the collateral's force-close ordering
+    fn get_force_close_candidates(&self, collateral_id: &TokenIdentifier) -> Vec<u64> {
+        let ordering = self.force_close_ordering(collateral_id).get();
+        let current_time = self.blockchain().get_block_timestamp();
+        let min_hedging_period_seconds = self.min_hedging_period_seconds().get();
+
+        let mut candidates = Vec::new();
+        for nft_nonce in self.open_position_nonces(collateral_id).iter() {
+            let hedging_position = self.hedging_position(nft_nonce).get();
+            if current_time - hedging_position.creation_timestamp < min_hedging_period_seconds {
+                continue;
+            }
+
+            let sort_key = self.get_force_close_sort_key(ordering, &hedging_position);
+            candidates.push((sort_key, nft_nonce));
+        }
+
+        candidates.sort_by(|first, second| first.0.cmp(&second.0).then(first.1.cmp(&second.1)));
+        if ordering == ForceCloseOrdering::HighestLeverageFirst {
+            candidates.reverse();
+        }
+
+        candidates
+            .into_iter()
+            .map(|(_, nft_nonce)| nft_nonce)
+            .collect()
+    }
+
+    fn get_force_close_sort_key(
         &self,
+        ordering: ForceCloseOrdering,
         hedging_position: &HedgingPosition<Self::Api>,
-    ) -> SCResult<BigUint> {
-        let collateral_value_in_dollars =
-            self.get_collateral_value_in_dollars(&hedging_position.collateral_id)?;
+    ) -> BigUint {
+        match ordering {
+            ForceCloseOrdering::OldestFirst => BigUint::from(hedging_position.creation_timestamp),
+            ForceCloseOrdering::HighestLeverageFirst => self.calculate_leverage(
+                &hedging_position.deposit_amount,
+                &hedging_position.covered_amount,
+            ),
+        }
+    }
 
-        // margin = x / y + (1 - initial_oracle / current_oracle)
-        // where x is deposit_amount and y is amount_to_cover
-        let amount_ratio = self.calculate_ratio(
-            &hedging_position.deposit_amount,
-            &hedging_position.covered_amount,
-        );
-        let price_ratio = self.calculate_ratio(
-            &hedging_position.oracle_value_at_deposit_time,
-            &collateral_value_in_dollars,
-        );
+    fn is_over_hedge_limit(&self, collateral_id: &TokenIdentifier) -> bool {
+        let pool = self.get_pool(collateral_id);
+        let limit_hedge_amount = self.calculate_limit_hedge_amount(&pool.collateral_amount);
+
+        pool.total_collateral_covered > limit_hedge_amount
+    }
 
-        let one = BigUint::from(ONE);
-        let result = if price_ratio <= one {
-            let diff = one - price_ratio;
-            amount_ratio + diff
+    // bounties are paid out of the pool's reserves, so they are capped at them
+    fn calculate_bounty_amount(
+        &self,
+        collateral_id: &TokenIdentifier,
+        bounty_percentage: &BigUint,
+        amount: &BigUint,
+    ) -> BigUint {
+        let bounty_amount = self.calculate_percentage_of(bounty_percentage, amount);
+        let reserves = self.get_pool_reserves(collateral_id);
+        if bounty_amount > reserves {
+            reserves
         } else {
-            let diff = price_ratio - one;
-            amount_ratio - diff
-        };
+            bounty_amount
+        }
+    }
+
+    fn is_position_of_collateral(&self, nft_nonce: u64, collateral_id: &TokenIdentifier) -> bool {
+        let mapper = self.hedging_position(nft_nonce);
+        !mapper.is_empty() && &mapper.get().collateral_id == collateral_id
+    }
+
+    // The reward is paid out of the accumulated tx fees, and only if the min interval
+    // has passed since the last rewarded call of the action for the collateral.
+    // Calls made before that still do their job, they just don't earn anything
+    fn pay_keeper_reward(&self, collateral_id: &TokenIdentifier, action: KeeperAction) {
+        let reward_amount = self.claim_keeper_reward(collateral_id, action);
+        if reward_amount == 0 {
+            return;
+        }
+
+        let caller = self.blockchain().get_caller();
+        self.send()
+            .direct(&caller, collateral_id, 0, &reward_amount, &[]);
+
+        self.emit_keeper_reward_event(collateral_id, action, &reward_amount);
+    }
 
-        Ok(result)
+    // Takes the reward out of the accumulated tx fees, capped at them.
+    // Only rewarded calls are recorded, so unrewarded ones can't push the next reward back
+    fn claim_keeper_reward(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: KeeperAction,
+    ) -> BigUint {
+        let current_time = self.blockchain().get_block_timestamp();
+        let last_call_timestamp = self
+            .last_keeper_action_timestamp(collateral_id, action)
+            .get();
+        let min_interval_seconds = self
+            .keeper_reward_min_interval_seconds(collateral_id, action)
+            .get();
+        if current_time - last_call_timestamp < min_interval_seconds {
+            return BigUint::zero();
+        }
+
+        let accumulated_fees = self.accumulated_tx_fees(collateral_id).get();
+        let mut reward_amount = self.keeper_reward_amount(collateral_id, action).get();
+        if reward_amount > accumulated_fees {
+            reward_amount = accumulated_fees;
+        }
+        if reward_amount == 0 {
+            return reward_amount;
+        }
+
+        self.last_keeper_action_timestamp(collateral_id, action)
+            .set(&current_time);
+        self.accumulated_tx_fees(collateral_id)
+            .update(|accumulated_fees| *accumulated_fees -= &reward_amount);
+
+        reward_amount
     }
+
+    // storage
+
+    #[view(getKeeperRewardAmount)]
+    #[storage_mapper("keeperRewardAmount")]
+    fn keeper_reward_amount(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: KeeperAction,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[view(getKeeperRewardMinIntervalSeconds)]
+    #[storage_mapper("keeperRewardMinIntervalSeconds")]
+    fn keeper_reward_min_interval_seconds(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: KeeperAction,
+    ) -> SingleValueMapper<u64>;
+
+    #[view(getLastKeeperActionTimestamp)]
+    #[storage_mapper("lastKeeperActionTimestamp")]
+    fn last_keeper_action_timestamp(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: KeeperAction,
+    ) -> SingleValueMapper<u64>;
 }
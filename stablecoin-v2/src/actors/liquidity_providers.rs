@@ -1,14 +1,18 @@
-use crate::math::ONE;
+use crate::{fees::FeeCurveType, math::ONE, pause::PausableAction};
 
 elrond_wasm::imports!();
 
 #[elrond_wasm::module]
 pub trait LiquidityProvidersModule:
-    crate::fees::FeesModule
+    crate::events::EventsModule
+    + crate::fees::FeesModule
     + crate::liquidity_token::LiquidityTokenModule
     + crate::math::MathModule
+    + crate::pause::PauseModule
     + crate::pools::PoolsModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
     + crate::token_common::TokenCommonModule
 {
     #[payable("*")]
@@ -19,6 +23,7 @@ pub trait LiquidityProvidersModule:
         #[payment_amount] payment_amount: BigUint,
     ) -> SCResult<()> {
         self.require_collateral_in_whitelist(&payment_token)?;
+        self.require_not_paused(&payment_token, PausableAction::LiquidityDeposit)?;
 
         self.update_pool(&payment_token, |pool| {
             pool.collateral_reserves += &payment_amount;
@@ -35,6 +40,8 @@ pub trait LiquidityProvidersModule:
         let caller = self.blockchain().get_caller();
         self.send_liq_tokens(&caller, sft_nonce, &amount_in_liq_tokens);
 
+        self.emit_add_liquidity_event(&payment_token, &payment_amount, &amount_in_liq_tokens);
+
         Ok(())
     }
 
@@ -46,6 +53,8 @@ pub trait LiquidityProvidersModule:
         #[payment_nonce] payment_nonce: u64,
         #[payment_amount] payment_amount: BigUint,
     ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
         let liq_token_id = self.liquidity_token_id().get();
         require!(
             payment_token == liq_token_id,
@@ -89,6 +98,13 @@ pub trait LiquidityProvidersModule:
             &[],
         );
 
+        self.emit_remove_liquidity_event(
+            &collateral_id,
+            &collateral_amount_after_slippage,
+            &payment_amount,
+            &slippage_amount_in_collateral,
+        );
+
         Ok(())
     }
 
@@ -111,20 +127,9 @@ pub trait LiquidityProvidersModule:
             return BigUint::zero();
         }
 
-        let (min_slippage_percentage, max_slippage_percentage) =
-            self.min_max_slippage_percentage(collateral_id).get();
-        let percentage_diff = &max_slippage_percentage - &min_slippage_percentage;
-
-        // min + (max - min) * hedging_ratio
-        min_slippage_percentage + self.multiply(&hedging_ratio, &percentage_diff, &one)
+        self.calculate_curve_fee_percentage(collateral_id, FeeCurveType::Slippage, &hedging_ratio)
     }
 
-    #[storage_mapper("minMaxSlippagePercentage")]
-    fn min_max_slippage_percentage(
-        &self,
-        collateral_id: &TokenIdentifier,
-    ) -> SingleValueMapper<(BigUint, BigUint)>;
-
     #[storage_mapper("liquidityProviderFeeRewardPercentage")]
     fn liq_provider_fee_reward_percentage(
         &self,
@@ -1,13 +1,21 @@
 elrond_wasm::imports!();
 
+use crate::{pause::PausableAction, twap::PricedOperation};
+
 #[elrond_wasm::module]
 pub trait StableSeekers:
-    crate::fees::FeesModule
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
     + crate::math::MathModule
+    + crate::pause::PauseModule
     + crate::pools::PoolsModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
     + crate::stablecoin_token::StablecoinTokenModule
     + crate::token_common::TokenCommonModule
+    + crate::twap::TwapModule
 {
     #[payable("*")]
     #[endpoint(sellCollateral)]
@@ -18,26 +26,42 @@ pub trait StableSeekers:
         min_amount_out: BigUint,
     ) -> SCResult<()> {
         self.require_collateral_in_whitelist(&payment_token)?;
+        self.require_not_paused(&payment_token, PausableAction::Mint)?;
 
-        let collateral_value_in_dollars = self.get_collateral_value_in_dollars(&payment_token)?;
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&payment_token, PricedOperation::Mint)?;
+        self.refresh_fee_configuration_if_stale(&payment_token);
         let transaction_fees_percentage = self.get_mint_transaction_fees_percentage(&payment_token);
         let fees_amount_in_collateral =
             self.calculate_percentage_of(&transaction_fees_percentage, &payment_amount);
         let collateral_amount = &payment_amount - &fees_amount_in_collateral;
 
-        let stablecoin_amount = &collateral_value_in_dollars * &collateral_amount;
+        let stablecoin_amount = self.get_collateral_value_in_stablecoin(
+            &payment_token,
+            &collateral_amount,
+            &collateral_value_in_dollars,
+        );
         require!(stablecoin_amount >= min_amount_out, "Below min amount");
 
+        self.update_funding_index(&payment_token);
         self.update_pool(&payment_token, |pool| {
             pool.collateral_amount += &collateral_amount;
             pool.stablecoin_amount += &stablecoin_amount;
         });
         self.accumulated_tx_fees(&payment_token)
-            .update(|accumulated_fees| *accumulated_fees += fees_amount_in_collateral);
+            .update(|accumulated_fees| *accumulated_fees += &fees_amount_in_collateral);
 
         let caller = self.blockchain().get_caller();
         self.mint_and_send_stablecoin(&caller, &stablecoin_amount);
 
+        self.emit_sell_collateral_event(
+            &payment_token,
+            &collateral_amount,
+            &stablecoin_amount,
+            &fees_amount_in_collateral,
+            &collateral_value_in_dollars,
+        );
+
         Ok(())
     }
 
@@ -56,9 +80,16 @@ pub trait StableSeekers:
             "May only pay with stablecoins"
         );
         self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::Burn)?;
 
-        let collateral_value_in_dollars = self.get_collateral_value_in_dollars(&collateral_id)?;
-        let total_value_in_collateral = &payment_amount / &collateral_value_in_dollars;
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::Burn)?;
+        let total_value_in_collateral = self.get_stablecoin_value_in_collateral(
+            &collateral_id,
+            &payment_amount,
+            &collateral_value_in_dollars,
+        );
+        self.refresh_fee_configuration_if_stale(&collateral_id);
         let transaction_fees_percentage = self.get_burn_transaction_fees_percentage(&collateral_id);
         let fees_amount_in_collateral =
             self.calculate_percentage_of(&transaction_fees_percentage, &total_value_in_collateral);
@@ -66,6 +97,7 @@ pub trait StableSeekers:
         let collateral_amount = &total_value_in_collateral - &fees_amount_in_collateral;
         require!(collateral_amount >= min_amount_out, "Below min amount");
 
+        self.update_funding_index(&collateral_id);
         self.update_pool(&collateral_id, |pool| {
             require!(
                 pool.collateral_amount >= collateral_amount,
@@ -82,7 +114,7 @@ pub trait StableSeekers:
             Ok(())
         })?;
         self.accumulated_tx_fees(&collateral_id)
-            .update(|accumulated_fees| *accumulated_fees += fees_amount_in_collateral);
+            .update(|accumulated_fees| *accumulated_fees += &fees_amount_in_collateral);
 
         self.burn_stablecoin(&payment_amount);
 
@@ -90,6 +122,14 @@ pub trait StableSeekers:
         self.send()
             .direct(&caller, &collateral_id, 0, &collateral_amount, &[]);
 
+        self.emit_buy_collateral_event(
+            &collateral_id,
+            &collateral_amount,
+            &payment_amount,
+            &fees_amount_in_collateral,
+            &collateral_value_in_dollars,
+        );
+
         Ok(())
     }
 }
@@ -0,0 +1,148 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::{
+    fees::{FeeBreakpoint, FeeCurveType},
+    hedging_agents::ForceCloseOrdering,
+    keepers::KeeperAction,
+    math::{ONE, PERCENTAGE_PRECISION},
+    twap::PricedOperation,
+};
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode)]
+pub struct KeeperRewardConfig<M: ManagedTypeApi> {
+    pub action: KeeperAction,
+    pub min_interval_seconds: u64,
+    pub reward_amount: BigUint<M>,
+}
+
+/// twap_priced_operations are the operations priced with the TWAP instead of the spot price
+#[derive(TypeAbi, TopEncode, TopDecode)]
+pub struct CollateralConfig<M: ManagedTypeApi> {
+    pub collateral_ticker: ManagedBuffer<M>,
+    pub collateral_num_decimals: u32,
+    pub max_leverage: BigUint<M>,
+    pub hedging_maintenance_ratio: BigUint<M>,
+    pub liq_provider_fee_reward_percentage: BigUint<M>,
+    pub mint_fee_curve: Vec<FeeBreakpoint<M>>,
+    pub burn_fee_curve: Vec<FeeBreakpoint<M>>,
+    pub hedging_open_fee_curve: Vec<FeeBreakpoint<M>>,
+    pub hedging_close_fee_curve: Vec<FeeBreakpoint<M>>,
+    pub slippage_curve: Vec<FeeBreakpoint<M>>,
+    pub max_hedging_period_seconds: u64,
+    pub close_order_bounty_percentage: BigUint<M>,
+    pub liquidation_bounty_percentage: BigUint<M>,
+    pub force_close_ordering: ForceCloseOrdering,
+    pub max_funding_rate_per_second: BigUint<M>,
+    pub treasury_fee_percentage: BigUint<M>,
+    pub max_price_age_seconds: u64,
+    pub max_price_deviation_percentage: BigUint<M>,
+    pub price_sources_quorum: usize,
+    pub twap_window_seconds: u64,
+    pub twap_priced_operations: Vec<PricedOperation>,
+    pub keeper_rewards: Vec<KeeperRewardConfig<M>>,
+}
+
+#[elrond_wasm::module]
+pub trait ConfigModule:
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
+    + crate::hedging_agents::HedgingAgentsModule
+    + crate::hedging_token::HedgingTokenModule
+    + crate::keepers::KeepersModule
+    + crate::liquidity_providers::LiquidityProvidersModule
+    + crate::liquidity_token::LiquidityTokenModule
+    + crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+    + crate::token_common::TokenCommonModule
+    + crate::treasury::TreasuryModule
+    + crate::twap::TwapModule
+{
+    #[view(getCollateralConfig)]
+    fn get_collateral_config(
+        &self,
+        collateral_id: TokenIdentifier,
+    ) -> SCResult<CollateralConfig<Self::Api>> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+
+        Ok(CollateralConfig {
+            collateral_ticker: self.collateral_ticker(&collateral_id).get(),
+            collateral_num_decimals: self.collateral_num_decimals(&collateral_id).get(),
+            max_leverage: self.max_leverage(&collateral_id).get(),
+            hedging_maintenance_ratio: self.hedging_maintenance_ratio(&collateral_id).get(),
+            liq_provider_fee_reward_percentage: self
+                .liq_provider_fee_reward_percentage(&collateral_id)
+                .get(),
+            mint_fee_curve: self.fee_curve(&collateral_id, FeeCurveType::Mint).get(),
+            burn_fee_curve: self.fee_curve(&collateral_id, FeeCurveType::Burn).get(),
+            hedging_open_fee_curve: self
+                .fee_curve(&collateral_id, FeeCurveType::HedgingOpen)
+                .get(),
+            hedging_close_fee_curve: self
+                .fee_curve(&collateral_id, FeeCurveType::HedgingClose)
+                .get(),
+            slippage_curve: self.fee_curve(&collateral_id, FeeCurveType::Slippage).get(),
+            max_hedging_period_seconds: self.max_hedging_period_seconds(&collateral_id).get(),
+            close_order_bounty_percentage: self.close_order_bounty_percentage(&collateral_id).get(),
+            liquidation_bounty_percentage: self.liquidation_bounty_percentage(&collateral_id).get(),
+            force_close_ordering: self.force_close_ordering(&collateral_id).get(),
+            max_funding_rate_per_second: self.max_funding_rate_per_second(&collateral_id).get(),
+            treasury_fee_percentage: self.treasury_fee_percentage(&collateral_id).get(),
+            max_price_age_seconds: self.max_price_age_seconds(&collateral_id).get(),
+            max_price_deviation_percentage: self
+                .max_price_deviation_percentage(&collateral_id)
+                .get(),
+            price_sources_quorum: self.price_sources_quorum(&collateral_id).get(),
+            twap_window_seconds: self.twap_window_seconds(&collateral_id).get(),
+            twap_priced_operations: PricedOperation::all()
+                .iter()
+                .filter(|operation| self.use_twap_price(&collateral_id, **operation).get())
+                .copied()
+                .collect(),
+            keeper_rewards: KeeperAction::all()
+                .iter()
+                .map(|action| KeeperRewardConfig {
+                    action: *action,
+                    min_interval_seconds: self
+                        .keeper_reward_min_interval_seconds(&collateral_id, *action)
+                        .get(),
+                    reward_amount: self.keeper_reward_amount(&collateral_id, *action).get(),
+                })
+                .collect(),
+        })
+    }
+
+    // leverage is (deposit + covered) / deposit, so it's always over ONE for open positions
+    fn require_valid_max_leverage(&self, max_leverage: &BigUint) -> SCResult<()> {
+        require!(*max_leverage > ONE, "Invalid max leverage");
+        Ok(())
+    }
+
+    // positions are liquidated once their margin ratio goes under the maintenance ratio
+    fn require_valid_hedging_maintenance_ratio(
+        &self,
+        hedging_maintenance_ratio: &BigUint,
+    ) -> SCResult<()> {
+        require!(
+            *hedging_maintenance_ratio > 0u64 && *hedging_maintenance_ratio < ONE,
+            "Invalid hedging maintenance ratio"
+        );
+        Ok(())
+    }
+
+    fn require_valid_liq_provider_fee_reward_percentage(
+        &self,
+        liq_provider_fee_reward_percentage: &BigUint,
+    ) -> SCResult<()> {
+        require!(
+            *liq_provider_fee_reward_percentage <= PERCENTAGE_PRECISION,
+            "Invalid liquidity provider fee reward percentage"
+        );
+        Ok(())
+    }
+}
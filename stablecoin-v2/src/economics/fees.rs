@@ -1,18 +1,69 @@
 elrond_wasm::imports!();
 elrond_wasm::derive_imports!();
 
-use crate::math::ONE;
+use crate::math::{ONE, PERCENTAGE_PRECISION};
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum FeeCurveType {
+    Mint,
+    Burn,
+    HedgingOpen,
+    HedgingClose,
+    Slippage,
+}
+
+impl FeeCurveType {
+    pub fn from_index(index: u64) -> Option<Self> {
+        match index {
+            0 => Some(FeeCurveType::Mint),
+            1 => Some(FeeCurveType::Burn),
+            2 => Some(FeeCurveType::HedgingOpen),
+            3 => Some(FeeCurveType::HedgingClose),
+            4 => Some(FeeCurveType::Slippage),
+            _ => None,
+        }
+    }
+
+    pub fn all() -> [Self; 5] {
+        [
+            FeeCurveType::Mint,
+            FeeCurveType::Burn,
+            FeeCurveType::HedgingOpen,
+            FeeCurveType::HedgingClose,
+            FeeCurveType::Slippage,
+        ]
+    }
+
+    // mint and hedger close fees decrease as the coverage ratio increases, the others increase
+    pub fn is_decreasing(&self) -> bool {
+        matches!(self, FeeCurveType::Mint | FeeCurveType::HedgingClose)
+    }
+}
+
+/// hedging_ratio is relative to the target, so ONE means the pool is exactly at the target.
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, Clone)]
+pub struct FeeBreakpoint<M: ManagedTypeApi> {
+    pub hedging_ratio: BigUint<M>,
+    pub fee_percentage: BigUint<M>,
+}
 
 #[derive(TopEncode, TopDecode)]
 pub struct CurrentFeeConfiguration<M: ManagedTypeApi> {
     pub hedging_ratio: BigUint<M>,
     pub mint_fee_percentage: BigUint<M>,
     pub burn_fee_percentage: BigUint<M>,
+    pub hedging_open_fee_percentage: BigUint<M>,
+    pub hedging_close_fee_percentage: BigUint<M>,
+    pub timestamp: u64,
 }
 
 #[elrond_wasm::module]
 pub trait FeesModule:
-    crate::math::MathModule + crate::pools::PoolsModule + price_aggregator_proxy::PriceAggregatorModule
+    crate::math::MathModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
 {
     #[view(getCurrentHedgingRatio)]
     fn get_current_hedging_ratio(&self, collateral_id: &TokenIdentifier) -> BigUint {
@@ -35,14 +86,22 @@ pub trait FeesModule:
             .burn_fee_percentage
     }
 
+    #[view(getFeeConfigurationTimestamp)]
+    fn get_fee_configuration_timestamp(&self, collateral_id: &TokenIdentifier) -> u64 {
+        self.current_fee_configuration(collateral_id)
+            .get()
+            .timestamp
+    }
+
     // The more collateral is covered, the more expensive it is to open a position
-    // This scales the same way as the burn transaction fees, so we use the same formula
     #[view(getHedgingPositionOpenTransactionFeesPercentage)]
     fn get_hedging_position_open_transaction_fees_percentage(
         &self,
         collateral_id: &TokenIdentifier,
     ) -> BigUint {
-        self.get_burn_transaction_fees_percentage(collateral_id)
+        self.current_fee_configuration(collateral_id)
+            .get()
+            .hedging_open_fee_percentage
     }
 
     // The more collateral is covered, the less expensive it is to exit
@@ -51,64 +110,227 @@ pub trait FeesModule:
         &self,
         collateral_id: &TokenIdentifier,
     ) -> BigUint {
-        self.get_mint_transaction_fees_percentage(collateral_id)
+        self.current_fee_configuration(collateral_id)
+            .get()
+            .hedging_close_fee_percentage
     }
 
-    // mint fees decrease as coverage ratio increases
-    fn calculate_mint_transaction_fees_percentage(
+    #[view(getFeeCurve)]
+    fn get_fee_curve(
+        &self,
+        collateral_id: TokenIdentifier,
+        curve_type: FeeCurveType,
+    ) -> MultiResultVec<MultiResult2<BigUint, BigUint>> {
+        self.fee_curve(&collateral_id, curve_type)
+            .get()
+            .into_iter()
+            .map(|breakpoint| {
+                MultiResult2::from((breakpoint.hedging_ratio, breakpoint.fee_percentage))
+            })
+            .collect()
+    }
+
+    fn update_fee_configuration(&self, collateral_id: &TokenIdentifier) {
+        let hedging_ratio = self.calculate_current_hedging_ratio(collateral_id);
+        let mint_fee_percentage =
+            self.calculate_curve_fee_percentage(collateral_id, FeeCurveType::Mint, &hedging_ratio);
+        let burn_fee_percentage =
+            self.calculate_curve_fee_percentage(collateral_id, FeeCurveType::Burn, &hedging_ratio);
+        let hedging_open_fee_percentage = self.calculate_curve_fee_percentage(
+            collateral_id,
+            FeeCurveType::HedgingOpen,
+            &hedging_ratio,
+        );
+        let hedging_close_fee_percentage = self.calculate_curve_fee_percentage(
+            collateral_id,
+            FeeCurveType::HedgingClose,
+            &hedging_ratio,
+        );
+
+        self.current_fee_configuration(collateral_id)
+            .set(&CurrentFeeConfiguration {
+                hedging_ratio,
+                mint_fee_percentage,
+                burn_fee_percentage,
+                hedging_open_fee_percentage,
+                hedging_close_fee_percentage,
+                timestamp: self.blockchain().get_block_timestamp(),
+            });
+    }
+
+    // the fees only depend on the hedging ratio, so the snapshot is only stale if the ratio moved
+    fn refresh_fee_configuration_if_stale(&self, collateral_id: &TokenIdentifier) {
+        let fee_configuration_mapper = self.current_fee_configuration(collateral_id);
+        if fee_configuration_mapper.is_empty()
+            || fee_configuration_mapper.get().hedging_ratio
+                != self.calculate_current_hedging_ratio(collateral_id)
+        {
+            self.update_fee_configuration(collateral_id);
+        }
+    }
+
+    // Linear interpolation between the two breakpoints around the hedging ratio.
+    // The fee is flat before the first breakpoint and after the last one
+    fn calculate_curve_fee_percentage(
         &self,
         collateral_id: &TokenIdentifier,
+        curve_type: FeeCurveType,
+        hedging_ratio: &BigUint,
     ) -> BigUint {
-        let target_hedging_ratio = self.target_hedging_ratio().get();
-        let current_hedging_ratio = self.calculate_current_hedging_ratio(collateral_id);
-        let (min_fees_percentage, max_fees_percentage) =
-            self.min_max_fees_percentage(collateral_id).get();
-        let one = BigUint::from(ONE);
-
-        if current_hedging_ratio == 0 {
-            return max_fees_percentage;
+        let curve = self.fee_curve(collateral_id, curve_type).get();
+        let (first, last) = match (curve.first(), curve.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return BigUint::zero(),
+        };
+        if hedging_ratio <= &first.hedging_ratio {
+            return first.fee_percentage.clone();
         }
-        if current_hedging_ratio >= target_hedging_ratio {
-            return min_fees_percentage;
+
+        for pair in curve.windows(2) {
+            let (left, right) = (&pair[0], &pair[1]);
+            if hedging_ratio > &right.hedging_ratio {
+                continue;
+            }
+
+            let ratio_diff = hedging_ratio - &left.hedging_ratio;
+            let ratio_range = &right.hedging_ratio - &left.hedging_ratio;
+            return if right.fee_percentage >= left.fee_percentage {
+                let fee_diff = &right.fee_percentage - &left.fee_percentage;
+                &left.fee_percentage + &(&(&fee_diff * &ratio_diff) / &ratio_range)
+            } else {
+                let fee_diff = &left.fee_percentage - &right.fee_percentage;
+                &left.fee_percentage - &(&(&fee_diff * &ratio_diff) / &ratio_range)
+            };
         }
 
-        let percentage_diff = &max_fees_percentage - &min_fees_percentage;
+        last.fee_percentage.clone()
+    }
 
-        // max - (max - min) * hedging_ratio
-        max_fees_percentage - self.multiply(&current_hedging_ratio, &percentage_diff, &one)
+    // from start at a hedging ratio of 0 to end at the target
+    fn create_linear_fee_curve(
+        &self,
+        start_fee_percentage: &BigUint,
+        end_fee_percentage: &BigUint,
+    ) -> Vec<FeeBreakpoint<Self::Api>> {
+        Vec::from([
+            FeeBreakpoint {
+                hedging_ratio: BigUint::zero(),
+                fee_percentage: start_fee_percentage.clone(),
+            },
+            FeeBreakpoint {
+                hedging_ratio: BigUint::from(ONE),
+                fee_percentage: end_fee_percentage.clone(),
+            },
+        ])
     }
 
-    // burn fees decrease as coverage ratio decreases
-    fn calculate_burn_transaction_fees_percentage(
+    // the fee curves start out linear between the min and max, and may be reshaped through governance
+    fn set_linear_fee_curves(
         &self,
         collateral_id: &TokenIdentifier,
-    ) -> BigUint {
-        let target_hedging_ratio = self.target_hedging_ratio().get();
-        let current_hedging_ratio = self.calculate_current_hedging_ratio(collateral_id);
-        let (min_fees_percentage, max_fees_percentage) =
-            self.min_max_fees_percentage(collateral_id).get();
-        let one = BigUint::from(ONE);
+        min_fees_percentage: &BigUint,
+        max_fees_percentage: &BigUint,
+        min_slippage_percentage: &BigUint,
+        max_slippage_percentage: &BigUint,
+    ) -> SCResult<()> {
+        let fees_curve = self.create_linear_fee_curve(min_fees_percentage, max_fees_percentage);
+        let reversed_fees_curve =
+            self.create_linear_fee_curve(max_fees_percentage, min_fees_percentage);
+        let slippage_curve =
+            self.create_linear_fee_curve(min_slippage_percentage, max_slippage_percentage);
+        for curve_type in FeeCurveType::all().iter() {
+            let curve = match curve_type {
+                FeeCurveType::Mint | FeeCurveType::HedgingClose => &reversed_fees_curve,
+                FeeCurveType::Burn | FeeCurveType::HedgingOpen => &fees_curve,
+                FeeCurveType::Slippage => &slippage_curve,
+            };
+            self.require_valid_fee_curve(*curve_type, curve)?;
+            self.fee_curve(collateral_id, *curve_type).set(curve);
+        }
+
+        Ok(())
+    }
 
-        if current_hedging_ratio == 0 {
-            return min_fees_percentage;
+    // collaterals whitelisted before the upgrade only have their min and max fees stored,
+    // and would be charged no fees at all without curves
+    fn migrate_legacy_fee_curves(&self, collateral_id: &TokenIdentifier) -> SCResult<()> {
+        let legacy_fees_mapper = self.min_max_fees_percentage(collateral_id);
+        let legacy_slippage_mapper = self.min_max_slippage_percentage(collateral_id);
+        if legacy_fees_mapper.is_empty() {
+            return Ok(());
         }
-        if current_hedging_ratio >= target_hedging_ratio {
-            return max_fees_percentage;
+
+        let (min_fees_percentage, max_fees_percentage) = legacy_fees_mapper.get();
+        let (min_slippage_percentage, max_slippage_percentage) = legacy_slippage_mapper.get();
+        self.set_linear_fee_curves(
+            collateral_id,
+            &min_fees_percentage,
+            &max_fees_percentage,
+            &min_slippage_percentage,
+            &max_slippage_percentage,
+        )?;
+        legacy_fees_mapper.clear();
+        legacy_slippage_mapper.clear();
+
+        // a configuration calculated before the migration would keep charging no fees
+        self.update_fee_configuration(collateral_id);
+
+        Ok(())
+    }
+
+    // values are the flattened (hedging ratio, fee) pairs
+    fn fee_curve_from_values(&self, values: &[BigUint]) -> Vec<FeeBreakpoint<Self::Api>> {
+        values
+            .chunks(2)
+            .map(|pair| FeeBreakpoint {
+                hedging_ratio: pair[0].clone(),
+                fee_percentage: pair[1].clone(),
+            })
+            .collect()
+    }
+
+    // the hedging ratios must be strictly increasing,
+    // and the fees must move in the curve's direction
+    fn require_valid_fee_curve(
+        &self,
+        curve_type: FeeCurveType,
+        curve: &[FeeBreakpoint<Self::Api>],
+    ) -> SCResult<()> {
+        require!(!curve.is_empty(), "Fee curve may not be empty");
+        for breakpoint in curve {
+            require!(
+                breakpoint.fee_percentage < PERCENTAGE_PRECISION,
+                "Invalid fee percentage"
+            );
         }
 
-        let percentage_diff = &max_fees_percentage - &min_fees_percentage;
+        for pair in curve.windows(2) {
+            let (left, right) = (&pair[0], &pair[1]);
+            require!(
+                left.hedging_ratio < right.hedging_ratio,
+                "Hedging ratios must be strictly increasing"
+            );
 
-        // min + (max - min) * hedging_ratio
-        min_fees_percentage + self.multiply(&current_hedging_ratio, &percentage_diff, &one)
+            let is_monotonic = if curve_type.is_decreasing() {
+                left.fee_percentage >= right.fee_percentage
+            } else {
+                left.fee_percentage <= right.fee_percentage
+            };
+            require!(is_monotonic, "Fee curve is not monotonic");
+        }
+
+        Ok(())
     }
 
+    // both amounts are in collateral units, so the ratio doesn't depend on the price or the decimals
     fn calculate_current_hedging_ratio(&self, collateral_id: &TokenIdentifier) -> BigUint {
         let pool = self.get_pool(collateral_id);
         let target_hedge_amount = self.calculate_target_hedge_amount(&pool.collateral_amount);
-        self.calculate_ratio(
-            &pool.total_covered_value_in_stablecoin,
-            &target_hedge_amount,
-        )
+        if target_hedge_amount == 0 {
+            return BigUint::zero();
+        }
+
+        self.calculate_ratio(&pool.total_collateral_covered, &target_hedge_amount)
     }
 
     fn calculate_target_hedge_amount(&self, collateral_amount: &BigUint) -> BigUint {
@@ -123,17 +345,34 @@ pub trait FeesModule:
 
     // storage
 
+    #[storage_mapper("feeCurve")]
+    fn fee_curve(
+        &self,
+        collateral_id: &TokenIdentifier,
+        curve_type: FeeCurveType,
+    ) -> SingleValueMapper<Vec<FeeBreakpoint<Self::Api>>>;
+
+    // the configuration stored under "currentFeeConfiguration" before the upgrade has fewer fields,
+    // so it is left behind and a new one is calculated on the first refresh
+    #[storage_mapper("feeConfiguration")]
+    fn current_fee_configuration(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<CurrentFeeConfiguration<Self::Api>>;
+
+    // pre-upgrade fee parameters, only read by the migration
+
     #[storage_mapper("minMaxFeesPercentage")]
     fn min_max_fees_percentage(
         &self,
         collateral_id: &TokenIdentifier,
     ) -> SingleValueMapper<(BigUint, BigUint)>;
 
-    #[storage_mapper("currentFeeConfiguration")]
-    fn current_fee_configuration(
+    #[storage_mapper("minMaxSlippagePercentage")]
+    fn min_max_slippage_percentage(
         &self,
         collateral_id: &TokenIdentifier,
-    ) -> SingleValueMapper<CurrentFeeConfiguration<Self::Api>>;
+    ) -> SingleValueMapper<(BigUint, BigUint)>;
 
     #[storage_mapper("accumulatedTxFees")]
     fn accumulated_tx_fees(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<BigUint>;
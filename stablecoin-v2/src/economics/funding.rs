@@ -0,0 +1,128 @@
+elrond_wasm::imports!();
+
+use crate::math::{ONE, PERCENTAGE_PRECISION};
+
+// The funding index is the cumulative funding, as a percentage of the deposit, owed by a position opened at index 0.
+// Hedgers only pay funding while the pool is over-hedged, scaling linearly from 0 at the target
+// up to the max funding rate at the hedging ratio limit
+#[elrond_wasm::module]
+pub trait FundingModule:
+    crate::fees::FeesModule
+    + crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+{
+    #[endpoint(updateFundingIndex)]
+    fn update_funding_index_endpoint(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+
+        self.update_funding_index(&collateral_id);
+
+        Ok(())
+    }
+
+    #[view(getCurrentFundingRatePerSecond)]
+    fn calculate_funding_rate_per_second(&self, collateral_id: &TokenIdentifier) -> BigUint {
+        let max_funding_rate = self.max_funding_rate_per_second(collateral_id).get();
+        if max_funding_rate == 0 || self.global_settlement_active().get() {
+            return BigUint::zero();
+        }
+
+        let pool = self.get_pool(collateral_id);
+        let target_hedge_amount = self.calculate_target_hedge_amount(&pool.collateral_amount);
+        if target_hedge_amount == 0 {
+            return BigUint::zero();
+        }
+
+        // the current hedging ratio is relative to the target, so ONE means the pool is exactly at the target
+        let one = BigUint::from(ONE);
+        let current_hedging_ratio = self.calculate_current_hedging_ratio(collateral_id);
+        if current_hedging_ratio <= one {
+            return BigUint::zero();
+        }
+
+        let target_hedging_ratio = self.target_hedging_ratio().get();
+        let hedging_ratio_limit = self.hedging_ratio_limit().get();
+        let limit_ratio = self.calculate_ratio(&hedging_ratio_limit, &target_hedging_ratio);
+        if current_hedging_ratio >= limit_ratio {
+            return max_funding_rate;
+        }
+
+        // max * (current - 1) / (limit - 1)
+        let ratio_over_target = &current_hedging_ratio - &one;
+        let limit_over_target = &limit_ratio - &one;
+        &(&max_funding_rate * &ratio_over_target) / &limit_over_target
+    }
+
+    #[view(getCurrentFundingIndex)]
+    fn get_current_funding_index(&self, collateral_id: &TokenIdentifier) -> BigUint {
+        let funding_index = self.cumulative_funding_index(collateral_id).get();
+        let last_update_timestamp = self.last_funding_update_timestamp(collateral_id).get();
+        if last_update_timestamp == 0 {
+            return funding_index;
+        }
+
+        let current_time = self.blockchain().get_block_timestamp();
+        let elapsed_seconds = current_time - last_update_timestamp;
+        let funding_rate = self.calculate_funding_rate_per_second(collateral_id);
+
+        funding_index + funding_rate * elapsed_seconds
+    }
+
+    // has to be called before any change to the pool's hedging ratio, so the previous rate is applied until now
+    fn update_funding_index(&self, collateral_id: &TokenIdentifier) -> BigUint {
+        let funding_index = self.get_current_funding_index(collateral_id);
+        self.cumulative_funding_index(collateral_id)
+            .set(&funding_index);
+        self.last_funding_update_timestamp(collateral_id)
+            .set(&self.blockchain().get_block_timestamp());
+
+        funding_index
+    }
+
+    // the target and the limit are shared by all the collaterals, so changing them changes every collateral's rate
+    fn update_all_funding_indexes(&self) {
+        for collateral_id in self.whitelisted_collaterals().iter() {
+            self.update_funding_index(&collateral_id);
+        }
+    }
+
+    // capped at the deposit
+    fn calculate_funding_amount(
+        &self,
+        deposit_amount: &BigUint,
+        funding_index_diff: &BigUint,
+    ) -> BigUint {
+        let funding_amount = deposit_amount * funding_index_diff / PERCENTAGE_PRECISION;
+        if &funding_amount < deposit_amount {
+            funding_amount
+        } else {
+            deposit_amount.clone()
+        }
+    }
+
+    // storage
+
+    #[storage_mapper("cumulativeFundingIndex")]
+    fn cumulative_funding_index(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[view(getLastFundingUpdateTimestamp)]
+    #[storage_mapper("lastFundingUpdateTimestamp")]
+    fn last_funding_update_timestamp(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<u64>;
+
+    #[view(getMaxFundingRatePerSecond)]
+    #[storage_mapper("maxFundingRatePerSecond")]
+    fn max_funding_rate_per_second(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+}
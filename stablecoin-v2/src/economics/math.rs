@@ -3,6 +3,10 @@ elrond_wasm::imports!();
 pub const PERCENTAGE_PRECISION: u64 = 1_000_000_000; // 100%
 pub const ONE: u64 = PERCENTAGE_PRECISION / 100;
 
+pub const STABLECOIN_NUM_DECIMALS: u32 = 6;
+// oracle prices are the dollar value of one whole collateral token, quoted the same way as the stablecoin
+pub const PRICE_NUM_DECIMALS: u32 = STABLECOIN_NUM_DECIMALS;
+
 // this is the most common case, and it's more efficient to have a constant instead of manually calculating 10^18 everytime
 const DEFAULT_TOKEN_NUM_DECIMALS: u32 = 18;
 const DEFAULT_TOKEN_DECIMALS_VALUE: u64 = 1_000_000_000_000_000_000;
@@ -29,6 +33,45 @@ pub trait MathModule {
         number * percentage / PERCENTAGE_PRECISION
     }
 
+    // value = amount * price, with the decimals of both removed and the stablecoin's added.
+    // Everything is multiplied before dividing, so precision is only lost once, rounding down
+    fn convert_collateral_to_stablecoin(
+        &self,
+        collateral_amount: &BigUint,
+        collateral_price: &BigUint,
+        collateral_num_decimals: u32,
+    ) -> BigUint {
+        self.scale_decimals(
+            &(collateral_amount * collateral_price),
+            collateral_num_decimals + PRICE_NUM_DECIMALS,
+            STABLECOIN_NUM_DECIMALS,
+        )
+    }
+
+    // amount = value / price, rounding down
+    fn convert_stablecoin_to_collateral(
+        &self,
+        stablecoin_amount: &BigUint,
+        collateral_price: &BigUint,
+        collateral_num_decimals: u32,
+    ) -> BigUint {
+        let scaled_stablecoin_amount = self.scale_decimals(
+            stablecoin_amount,
+            STABLECOIN_NUM_DECIMALS,
+            collateral_num_decimals + PRICE_NUM_DECIMALS,
+        );
+
+        &scaled_stablecoin_amount / collateral_price
+    }
+
+    fn scale_decimals(&self, amount: &BigUint, from_decimals: u32, to_decimals: u32) -> BigUint {
+        if from_decimals <= to_decimals {
+            amount * &self.create_precision_biguint(to_decimals - from_decimals)
+        } else {
+            amount / &self.create_precision_biguint(from_decimals - to_decimals)
+        }
+    }
+
     fn create_precision_biguint(&self, nr_decimals: u32) -> BigUint {
         if nr_decimals == DEFAULT_TOKEN_NUM_DECIMALS {
             return BigUint::from(DEFAULT_TOKEN_DECIMALS_VALUE);
@@ -1,3 +1,5 @@
 pub mod fees;
+pub mod funding;
 pub mod math;
 pub mod pools;
+pub mod treasury;
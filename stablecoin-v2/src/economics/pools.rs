@@ -1,9 +1,7 @@
 elrond_wasm::imports!();
 elrond_wasm::derive_imports!();
 
-use price_aggregator_proxy::DOLLAR_TICKER;
-
-#[derive(TypeAbi, TopEncode, TopDecode)]
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode)]
 pub struct Pool<M: ManagedTypeApi> {
     pub collateral_amount: BigUint<M>,
     pub stablecoin_amount: BigUint<M>,
@@ -26,7 +24,10 @@ impl<M: ManagedTypeApi> Pool<M> {
 
 #[elrond_wasm::module]
 pub trait PoolsModule:
-    crate::math::MathModule + price_aggregator_proxy::PriceAggregatorModule
+    crate::math::MathModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
 {
     #[inline(always)]
     fn get_pool(&self, collateral_id: &TokenIdentifier) -> Pool<Self::Api> {
@@ -65,11 +66,52 @@ pub trait PoolsModule:
     fn get_collateral_value_in_dollars(
         &self,
         collateral_id: &TokenIdentifier,
+    ) -> SCResult<BigUint> {
+        let collateral_value_in_dollars =
+            self.try_get_collateral_value_in_dollars(collateral_id)?;
+
+        self.require_valid_price(collateral_id, &collateral_value_in_dollars)?;
+        self.update_last_accepted_price(collateral_id, &collateral_value_in_dollars);
+
+        Ok(collateral_value_in_dollars)
+    }
+
+    // the price is not checked against the last accepted one
+    fn try_get_collateral_value_in_dollars(
+        &self,
+        collateral_id: &TokenIdentifier,
     ) -> SCResult<BigUint> {
         let collateral_ticker = self.collateral_ticker(collateral_id).get();
-        self.get_price_for_pair(collateral_ticker, ManagedBuffer::from(DOLLAR_TICKER))
-            .ok_or("Could not get collateral value in dollars")
-            .into()
+        let collateral_precision = self.get_collateral_precision(collateral_id);
+        self.get_price_from_sources(collateral_id, &collateral_ticker, &collateral_precision)
+    }
+
+    fn get_collateral_value_in_stablecoin(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        collateral_price: &BigUint,
+    ) -> BigUint {
+        let collateral_num_decimals = self.collateral_num_decimals(collateral_id).get();
+        self.convert_collateral_to_stablecoin(
+            collateral_amount,
+            collateral_price,
+            collateral_num_decimals,
+        )
+    }
+
+    fn get_stablecoin_value_in_collateral(
+        &self,
+        collateral_id: &TokenIdentifier,
+        stablecoin_amount: &BigUint,
+        collateral_price: &BigUint,
+    ) -> BigUint {
+        let collateral_num_decimals = self.collateral_num_decimals(collateral_id).get();
+        self.convert_stablecoin_to_collateral(
+            stablecoin_amount,
+            collateral_price,
+            collateral_num_decimals,
+        )
     }
 
     fn get_collateral_precision(&self, collateral_id: &TokenIdentifier) -> BigUint {
@@ -96,6 +138,11 @@ pub trait PoolsModule:
     #[storage_mapper("collateralWhitelisted")]
     fn collateral_whitelisted(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<bool>;
 
+    // only used to iterate over the collaterals, collateralWhitelisted stays the source of truth
+    #[view(getWhitelistedCollaterals)]
+    #[storage_mapper("whitelistedCollaterals")]
+    fn whitelisted_collaterals(&self) -> SetMapper<TokenIdentifier>;
+
     #[view(getCollateralTicker)]
     #[storage_mapper("collateralTicker")]
     fn collateral_ticker(
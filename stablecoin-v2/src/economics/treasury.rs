@@ -0,0 +1,146 @@
+elrond_wasm::imports!();
+
+use crate::twap::PricedOperation;
+
+// The treasury's share is taken out of the fees that would otherwise go to the reserves.
+// The treasury balance is kept apart from the pool, so it doesn't back anyone's claims.
+// Nothing may be withdrawn during global settlement, as the whole balance is set aside for the claims.
+// The treasury address is set through governance, like the treasury fee
+#[elrond_wasm::module]
+pub trait TreasuryModule:
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
+    + crate::hedging_agents::HedgingAgentsModule
+    + crate::hedging_token::HedgingTokenModule
+    + crate::liquidity_token::LiquidityTokenModule
+    + crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+    + crate::token_common::TokenCommonModule
+    + crate::twap::TwapModule
+{
+    #[only_owner]
+    #[endpoint(withdrawTreasuryFunds)]
+    fn withdraw_treasury_funds(
+        &self,
+        collateral_id: TokenIdentifier,
+        amount: BigUint,
+    ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+        require!(
+            !self.treasury_address().is_empty(),
+            "Treasury address not set"
+        );
+        require!(
+            amount <= self.treasury_balance(&collateral_id).get(),
+            "Not enough funds in treasury"
+        );
+        require!(
+            amount <= self.get_withdrawable_treasury_amount(collateral_id.clone())?,
+            "Withdrawal would leave the pool underfunded"
+        );
+
+        self.treasury_balance(&collateral_id)
+            .update(|balance| *balance -= &amount);
+
+        let treasury_address = self.treasury_address().get();
+        self.send()
+            .direct(&treasury_address, &collateral_id, 0, &amount, &[]);
+
+        self.emit_withdraw_treasury_funds_event(&collateral_id, &amount, &treasury_address);
+
+        Ok(())
+    }
+
+    #[view(getTreasuryBalances)]
+    fn get_treasury_balances(&self) -> MultiResultVec<MultiResult2<TokenIdentifier, BigUint>> {
+        let mut balances: Vec<MultiResult2<TokenIdentifier, BigUint>> = Vec::new();
+        for collateral_id in self.whitelisted_collaterals().iter() {
+            let balance = self.treasury_balance(&collateral_id).get();
+            balances.push((collateral_id, balance).into());
+        }
+
+        balances.into_iter().collect()
+    }
+
+    // hedger claims depend on the current price
+    #[view(getWithdrawableTreasuryAmount)]
+    fn get_withdrawable_treasury_amount(
+        &self,
+        collateral_id: TokenIdentifier,
+    ) -> SCResult<BigUint> {
+        if self.global_settlement_active().get() {
+            return Ok(BigUint::zero());
+        }
+
+        let collateral_value_in_dollars =
+            self.get_collateral_price(&collateral_id, PricedOperation::HedgingClose)?;
+
+        Ok(self
+            .calculate_withdrawable_treasury_amount(&collateral_id, &collateral_value_in_dollars))
+    }
+
+    // Whatever the contract holds on top of what it owes: the stablecoin holders' collateral,
+    // the fees that are yet to be split, the liquidity providers' share, including the fees credited to it,
+    // and the hedgers' deposits with their profit or loss, including unclaimed force-close withdrawals
+    fn calculate_withdrawable_treasury_amount(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        let owed_amount = self.calculate_owed_amount(collateral_id, collateral_value_in_dollars);
+        let sc_balance = self.blockchain().get_sc_balance(collateral_id, 0);
+        if sc_balance <= owed_amount {
+            return BigUint::zero();
+        }
+
+        let surplus = &sc_balance - &owed_amount;
+        let treasury_balance = self.treasury_balance(collateral_id).get();
+        if surplus < treasury_balance {
+            surplus
+        } else {
+            treasury_balance
+        }
+    }
+
+    fn calculate_owed_amount(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_value_in_dollars: &BigUint,
+    ) -> BigUint {
+        let pool = self.get_pool(collateral_id);
+        let mut owed_amount =
+            pool.collateral_amount + self.accumulated_tx_fees(collateral_id).get();
+
+        let sft_nonce = self.liq_sft_nonce_for_collateral(collateral_id).get();
+        if sft_nonce > 0 {
+            owed_amount += &self.collateral_amount_for_liq_token(sft_nonce).get();
+        }
+
+        owed_amount +=
+            &self.calculate_hedgers_liability(collateral_id, collateral_value_in_dollars);
+
+        owed_amount
+    }
+
+    // storage
+
+    #[view(getTreasuryAddress)]
+    #[storage_mapper("treasuryAddress")]
+    fn treasury_address(&self) -> SingleValueMapper<ManagedAddress>;
+
+    #[view(getTreasuryBalance)]
+    #[storage_mapper("treasuryBalance")]
+    fn treasury_balance(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
+    #[view(getTreasuryFeePercentage)]
+    #[storage_mapper("treasuryFeePercentage")]
+    fn treasury_fee_percentage(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+}
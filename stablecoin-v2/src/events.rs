@@ -0,0 +1,1127 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::{governance::Proposal, keepers::KeeperAction, pools::Pool};
+
+/// Fields shared by all the per-collateral events:
+/// who triggered the action, when, and the pool's state right after it
+#[derive(TypeAbi, TopEncode)]
+pub struct EventHeader<M: ManagedTypeApi> {
+    pub caller: ManagedAddress<M>,
+    pub collateral_id: TokenIdentifier<M>,
+    pub pool: Pool<M>,
+    pub block: u64,
+    pub epoch: u64,
+    pub timestamp: u64,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct SwapEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub collateral_amount: BigUint<M>,
+    pub stablecoin_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct OpenHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub deposit_amount: BigUint<M>,
+    pub covered_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct HedgingTokenEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct MarginEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub margin_amount: BigUint<M>,
+    pub new_deposit_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct IncreaseCoverageEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub added_covered_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+    pub new_deposit_amount: BigUint<M>,
+    pub new_covered_amount: BigUint<M>,
+    pub new_oracle_value_at_deposit_time: BigUint<M>,
+}
+
+/// oracle_price and fees_amount are None if the position was force-closed beforehand,
+/// as the settlement already happened in the force-close
+#[derive(TypeAbi, TopEncode)]
+pub struct CloseHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub collateral_amount: BigUint<M>,
+    pub liq_tokens_amount: BigUint<M>,
+    pub fees_amount: Option<BigUint<M>>,
+    pub oracle_price: Option<BigUint<M>>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct PartialCloseHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub closed_covered_amount: BigUint<M>,
+    pub collateral_amount: BigUint<M>,
+    pub liq_tokens_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+    pub remaining_deposit_amount: BigUint<M>,
+    pub remaining_covered_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct ExecuteCloseOrderEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub withdraw_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub bounty_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct ForceCloseHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub withdraw_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct PartialForceCloseHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub closed_covered_amount: BigUint<M>,
+    pub withdraw_amount: BigUint<M>,
+    pub fees_amount: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+    pub remaining_deposit_amount: BigUint<M>,
+    pub remaining_covered_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct LiquidateHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub deposit_amount: BigUint<M>,
+    pub covered_amount: BigUint<M>,
+    pub margin_ratio: BigUint<M>,
+    pub oracle_price: BigUint<M>,
+    pub bounty_amount: BigUint<M>,
+    pub reserves_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct LiquidityEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub collateral_amount: BigUint<M>,
+    pub liq_tokens_amount: BigUint<M>,
+    pub slippage_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct RebalancePoolEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub oracle_price: BigUint<M>,
+    pub old_stablecoin_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct SplitFeesEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub accumulated_fees: BigUint<M>,
+    pub liq_provider_reward: BigUint<M>,
+    pub treasury_amount: BigUint<M>,
+    pub reserves_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct WithdrawTreasuryFundsEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub amount: BigUint<M>,
+    pub treasury_address: ManagedAddress<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct KeeperRewardEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub action: KeeperAction,
+    pub reward_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct GlobalSettlementEvent<M: ManagedTypeApi> {
+    pub caller: ManagedAddress<M>,
+    pub block: u64,
+    pub epoch: u64,
+    pub timestamp: u64,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct FreezeSettlementPriceEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub settlement_price: BigUint<M>,
+    pub stablecoin_claim: BigUint<M>,
+}
+
+/// hedgers_claim is the part of the collateral set aside for the hedging positions
+/// still open or waiting to be withdrawn after a force-close,
+/// out of the hedgers_claim_total they are owed at the settlement price
+#[derive(TypeAbi, TopEncode)]
+pub struct SettlementHedgersClaimEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub hedgers_claim: BigUint<M>,
+    pub hedgers_claim_total: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct SettlementRedeemStablecoinsEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub stablecoin_amount: BigUint<M>,
+    pub collateral_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct SettlementCloseHedgingPositionEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub nft_nonce: u64,
+    pub collateral_amount: BigUint<M>,
+}
+
+#[derive(TypeAbi, TopEncode)]
+pub struct SettlementRedeemLiquidityEvent<M: ManagedTypeApi> {
+    pub header: EventHeader<M>,
+    pub liq_tokens_amount: BigUint<M>,
+    pub collateral_amount: BigUint<M>,
+}
+
+#[elrond_wasm::module]
+pub trait EventsModule:
+    crate::math::MathModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+{
+    fn create_event_header(&self, collateral_id: &TokenIdentifier) -> EventHeader<Self::Api> {
+        EventHeader {
+            caller: self.blockchain().get_caller(),
+            collateral_id: collateral_id.clone(),
+            pool: self.get_pool(collateral_id),
+            block: self.blockchain().get_block_nonce(),
+            epoch: self.blockchain().get_block_epoch(),
+            timestamp: self.blockchain().get_block_timestamp(),
+        }
+    }
+
+    fn emit_sell_collateral_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        stablecoin_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = SwapEvent {
+            header: self.create_event_header(collateral_id),
+            collateral_amount: collateral_amount.clone(),
+            stablecoin_amount: stablecoin_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.sell_collateral_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_buy_collateral_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        stablecoin_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = SwapEvent {
+            header: self.create_event_header(collateral_id),
+            collateral_amount: collateral_amount.clone(),
+            stablecoin_amount: stablecoin_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.buy_collateral_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_open_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        deposit_amount: &BigUint,
+        covered_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = OpenHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            deposit_amount: deposit_amount.clone(),
+            covered_amount: covered_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.open_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_deposit_hedging_token_event(&self, nft_nonce: u64, collateral_id: &TokenIdentifier) {
+        let event = HedgingTokenEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+        };
+        self.deposit_hedging_token_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_withdraw_hedging_token_event(&self, nft_nonce: u64, collateral_id: &TokenIdentifier) {
+        let event = HedgingTokenEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+        };
+        self.withdraw_hedging_token_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_add_margin_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        margin_amount: &BigUint,
+        new_deposit_amount: &BigUint,
+    ) {
+        let event = MarginEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            margin_amount: margin_amount.clone(),
+            new_deposit_amount: new_deposit_amount.clone(),
+        };
+        self.add_margin_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_increase_coverage_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        added_covered_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+        new_deposit_amount: &BigUint,
+        new_covered_amount: &BigUint,
+        new_oracle_value_at_deposit_time: &BigUint,
+    ) {
+        let event = IncreaseCoverageEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            added_covered_amount: added_covered_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+            new_deposit_amount: new_deposit_amount.clone(),
+            new_covered_amount: new_covered_amount.clone(),
+            new_oracle_value_at_deposit_time: new_oracle_value_at_deposit_time.clone(),
+        };
+        self.increase_coverage_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_remove_margin_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        margin_amount: &BigUint,
+        new_deposit_amount: &BigUint,
+    ) {
+        let event = MarginEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            margin_amount: margin_amount.clone(),
+            new_deposit_amount: new_deposit_amount.clone(),
+        };
+        self.remove_margin_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_close_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        liq_tokens_amount: &BigUint,
+        opt_fees_amount: Option<BigUint>,
+        opt_oracle_price: Option<BigUint>,
+    ) {
+        let event = CloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            collateral_amount: collateral_amount.clone(),
+            liq_tokens_amount: liq_tokens_amount.clone(),
+            fees_amount: opt_fees_amount,
+            oracle_price: opt_oracle_price,
+        };
+        self.close_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_partial_close_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        closed_covered_amount: &BigUint,
+        collateral_amount: &BigUint,
+        liq_tokens_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+        remaining_deposit_amount: &BigUint,
+        remaining_covered_amount: &BigUint,
+    ) {
+        let event = PartialCloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            closed_covered_amount: closed_covered_amount.clone(),
+            collateral_amount: collateral_amount.clone(),
+            liq_tokens_amount: liq_tokens_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+            remaining_deposit_amount: remaining_deposit_amount.clone(),
+            remaining_covered_amount: remaining_covered_amount.clone(),
+        };
+        self.partial_close_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_force_close_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        withdraw_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = ForceCloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            withdraw_amount: withdraw_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.force_close_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_partial_force_close_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        closed_covered_amount: &BigUint,
+        withdraw_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+        remaining_deposit_amount: &BigUint,
+        remaining_covered_amount: &BigUint,
+    ) {
+        let event = PartialForceCloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            closed_covered_amount: closed_covered_amount.clone(),
+            withdraw_amount: withdraw_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+            remaining_deposit_amount: remaining_deposit_amount.clone(),
+            remaining_covered_amount: remaining_covered_amount.clone(),
+        };
+        self.partial_force_close_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_expire_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        withdraw_amount: &BigUint,
+        fees_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = ForceCloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            withdraw_amount: withdraw_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.expire_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_execute_close_order_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        withdraw_amount: &BigUint,
+        fees_amount: &BigUint,
+        bounty_amount: &BigUint,
+        oracle_price: &BigUint,
+    ) {
+        let event = ExecuteCloseOrderEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            withdraw_amount: withdraw_amount.clone(),
+            fees_amount: fees_amount.clone(),
+            bounty_amount: bounty_amount.clone(),
+            oracle_price: oracle_price.clone(),
+        };
+        self.execute_close_order_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn emit_liquidate_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        deposit_amount: &BigUint,
+        covered_amount: &BigUint,
+        margin_ratio: &BigUint,
+        oracle_price: &BigUint,
+        bounty_amount: &BigUint,
+        reserves_amount: &BigUint,
+    ) {
+        let event = LiquidateHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            deposit_amount: deposit_amount.clone(),
+            covered_amount: covered_amount.clone(),
+            margin_ratio: margin_ratio.clone(),
+            oracle_price: oracle_price.clone(),
+            bounty_amount: bounty_amount.clone(),
+            reserves_amount: reserves_amount.clone(),
+        };
+        self.liquidate_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_add_liquidity_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        liq_tokens_amount: &BigUint,
+    ) {
+        let event = LiquidityEvent {
+            header: self.create_event_header(collateral_id),
+            collateral_amount: collateral_amount.clone(),
+            liq_tokens_amount: liq_tokens_amount.clone(),
+            slippage_amount: BigUint::zero(),
+        };
+        self.add_liquidity_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_remove_liquidity_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+        liq_tokens_amount: &BigUint,
+        slippage_amount: &BigUint,
+    ) {
+        let event = LiquidityEvent {
+            header: self.create_event_header(collateral_id),
+            collateral_amount: collateral_amount.clone(),
+            liq_tokens_amount: liq_tokens_amount.clone(),
+            slippage_amount: slippage_amount.clone(),
+        };
+        self.remove_liquidity_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_rebalance_pool_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        oracle_price: &BigUint,
+        old_stablecoin_amount: &BigUint,
+    ) {
+        let event = RebalancePoolEvent {
+            header: self.create_event_header(collateral_id),
+            oracle_price: oracle_price.clone(),
+            old_stablecoin_amount: old_stablecoin_amount.clone(),
+        };
+        self.rebalance_pool_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_split_fees_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        accumulated_fees: &BigUint,
+        liq_provider_reward: &BigUint,
+        treasury_amount: &BigUint,
+        reserves_amount: &BigUint,
+    ) {
+        let event = SplitFeesEvent {
+            header: self.create_event_header(collateral_id),
+            accumulated_fees: accumulated_fees.clone(),
+            liq_provider_reward: liq_provider_reward.clone(),
+            treasury_amount: treasury_amount.clone(),
+            reserves_amount: reserves_amount.clone(),
+        };
+        self.split_fees_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_withdraw_treasury_funds_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        amount: &BigUint,
+        treasury_address: &ManagedAddress,
+    ) {
+        let event = WithdrawTreasuryFundsEvent {
+            header: self.create_event_header(collateral_id),
+            amount: amount.clone(),
+            treasury_address: treasury_address.clone(),
+        };
+        self.withdraw_treasury_funds_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_keeper_reward_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: KeeperAction,
+        reward_amount: &BigUint,
+    ) {
+        let event = KeeperRewardEvent {
+            header: self.create_event_header(collateral_id),
+            action,
+            reward_amount: reward_amount.clone(),
+        };
+        self.keeper_reward_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_global_settlement_event(&self) {
+        let event = GlobalSettlementEvent {
+            caller: self.blockchain().get_caller(),
+            block: self.blockchain().get_block_nonce(),
+            epoch: self.blockchain().get_block_epoch(),
+            timestamp: self.blockchain().get_block_timestamp(),
+        };
+        self.global_settlement_event(&event.caller, event.epoch, &event);
+    }
+
+    fn emit_freeze_settlement_price_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        settlement_price: &BigUint,
+        stablecoin_claim: &BigUint,
+    ) {
+        let event = FreezeSettlementPriceEvent {
+            header: self.create_event_header(collateral_id),
+            settlement_price: settlement_price.clone(),
+            stablecoin_claim: stablecoin_claim.clone(),
+        };
+        self.freeze_settlement_price_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_settlement_hedgers_claim_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        hedgers_claim: &BigUint,
+        hedgers_claim_total: &BigUint,
+    ) {
+        let event = SettlementHedgersClaimEvent {
+            header: self.create_event_header(collateral_id),
+            hedgers_claim: hedgers_claim.clone(),
+            hedgers_claim_total: hedgers_claim_total.clone(),
+        };
+        self.settlement_hedgers_claim_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_settlement_redeem_stablecoins_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        stablecoin_amount: &BigUint,
+        collateral_amount: &BigUint,
+    ) {
+        let event = SettlementRedeemStablecoinsEvent {
+            header: self.create_event_header(collateral_id),
+            stablecoin_amount: stablecoin_amount.clone(),
+            collateral_amount: collateral_amount.clone(),
+        };
+        self.settlement_redeem_stablecoins_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_settlement_close_hedging_position_event(
+        &self,
+        nft_nonce: u64,
+        collateral_id: &TokenIdentifier,
+        collateral_amount: &BigUint,
+    ) {
+        let event = SettlementCloseHedgingPositionEvent {
+            header: self.create_event_header(collateral_id),
+            nft_nonce,
+            collateral_amount: collateral_amount.clone(),
+        };
+        self.settlement_close_hedging_position_event(
+            &event.header.caller,
+            collateral_id,
+            nft_nonce,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_settlement_redeem_liquidity_event(
+        &self,
+        collateral_id: &TokenIdentifier,
+        liq_tokens_amount: &BigUint,
+        collateral_amount: &BigUint,
+    ) {
+        let event = SettlementRedeemLiquidityEvent {
+            header: self.create_event_header(collateral_id),
+            liq_tokens_amount: liq_tokens_amount.clone(),
+            collateral_amount: collateral_amount.clone(),
+        };
+        self.settlement_redeem_liquidity_event(
+            &event.header.caller,
+            collateral_id,
+            event.header.epoch,
+            &event,
+        );
+    }
+
+    fn emit_propose_event(&self, proposal: &Proposal<Self::Api>) {
+        let caller = self.blockchain().get_caller();
+        self.propose_event(&caller, proposal.id, proposal);
+    }
+
+    fn emit_execute_proposal_event(&self, proposal: &Proposal<Self::Api>) {
+        let caller = self.blockchain().get_caller();
+        self.execute_proposal_event(&caller, proposal.id, proposal);
+    }
+
+    fn emit_cancel_proposal_event(&self, proposal: &Proposal<Self::Api>) {
+        let caller = self.blockchain().get_caller();
+        self.cancel_proposal_event(&caller, proposal.id, proposal);
+    }
+
+    // events
+
+    #[event("sellCollateral")]
+    fn sell_collateral_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        swap_event: &SwapEvent<Self::Api>,
+    );
+
+    #[event("buyCollateral")]
+    fn buy_collateral_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        swap_event: &SwapEvent<Self::Api>,
+    );
+
+    #[event("openHedgingPosition")]
+    fn open_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        open_event: &OpenHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("depositHedgingToken")]
+    fn deposit_hedging_token_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        deposit_event: &HedgingTokenEvent<Self::Api>,
+    );
+
+    #[event("withdrawHedgingToken")]
+    fn withdraw_hedging_token_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        withdraw_event: &HedgingTokenEvent<Self::Api>,
+    );
+
+    #[event("addMargin")]
+    fn add_margin_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        margin_event: &MarginEvent<Self::Api>,
+    );
+
+    #[event("increaseCoverage")]
+    fn increase_coverage_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        increase_coverage_event: &IncreaseCoverageEvent<Self::Api>,
+    );
+
+    #[event("removeMargin")]
+    fn remove_margin_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        margin_event: &MarginEvent<Self::Api>,
+    );
+
+    #[event("closeHedgingPosition")]
+    fn close_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        close_event: &CloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("partialCloseHedgingPosition")]
+    fn partial_close_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        partial_close_event: &PartialCloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("forceCloseHedgingPosition")]
+    fn force_close_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        force_close_event: &ForceCloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("partialForceCloseHedgingPosition")]
+    fn partial_force_close_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        partial_force_close_event: &PartialForceCloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("expireHedgingPosition")]
+    fn expire_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        expire_event: &ForceCloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("executeCloseOrder")]
+    fn execute_close_order_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        execute_close_order_event: &ExecuteCloseOrderEvent<Self::Api>,
+    );
+
+    #[event("liquidateHedgingPosition")]
+    fn liquidate_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        liquidate_event: &LiquidateHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("addLiquidity")]
+    fn add_liquidity_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        liquidity_event: &LiquidityEvent<Self::Api>,
+    );
+
+    #[event("removeLiquidity")]
+    fn remove_liquidity_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        liquidity_event: &LiquidityEvent<Self::Api>,
+    );
+
+    #[event("rebalancePool")]
+    fn rebalance_pool_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        rebalance_event: &RebalancePoolEvent<Self::Api>,
+    );
+
+    #[event("splitFees")]
+    fn split_fees_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        split_fees_event: &SplitFeesEvent<Self::Api>,
+    );
+
+    #[event("withdrawTreasuryFunds")]
+    fn withdraw_treasury_funds_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        withdraw_treasury_funds_event: &WithdrawTreasuryFundsEvent<Self::Api>,
+    );
+
+    #[event("keeperReward")]
+    fn keeper_reward_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        keeper_reward_event: &KeeperRewardEvent<Self::Api>,
+    );
+
+    #[event("propose")]
+    fn propose_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] proposal_id: u64,
+        proposal: &Proposal<Self::Api>,
+    );
+
+    #[event("executeProposal")]
+    fn execute_proposal_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] proposal_id: u64,
+        proposal: &Proposal<Self::Api>,
+    );
+
+    #[event("cancelProposal")]
+    fn cancel_proposal_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] proposal_id: u64,
+        proposal: &Proposal<Self::Api>,
+    );
+
+    #[event("globalSettlement")]
+    fn global_settlement_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] epoch: u64,
+        global_settlement_event: &GlobalSettlementEvent<Self::Api>,
+    );
+
+    #[event("freezeSettlementPrice")]
+    fn freeze_settlement_price_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        freeze_event: &FreezeSettlementPriceEvent<Self::Api>,
+    );
+
+    #[event("settlementHedgersClaim")]
+    fn settlement_hedgers_claim_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        hedgers_claim_event: &SettlementHedgersClaimEvent<Self::Api>,
+    );
+
+    #[event("settlementRedeemStablecoins")]
+    fn settlement_redeem_stablecoins_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        redeem_event: &SettlementRedeemStablecoinsEvent<Self::Api>,
+    );
+
+    #[event("settlementCloseHedgingPosition")]
+    fn settlement_close_hedging_position_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] nft_nonce: u64,
+        #[indexed] epoch: u64,
+        close_event: &SettlementCloseHedgingPositionEvent<Self::Api>,
+    );
+
+    #[event("settlementRedeemLiquidity")]
+    fn settlement_redeem_liquidity_event(
+        &self,
+        #[indexed] caller: &ManagedAddress,
+        #[indexed] collateral_id: &TokenIdentifier,
+        #[indexed] epoch: u64,
+        redeem_event: &SettlementRedeemLiquidityEvent<Self::Api>,
+    );
+}
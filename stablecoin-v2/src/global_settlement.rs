@@ -0,0 +1,549 @@
+elrond_wasm::imports!();
+
+use crate::hedging_agents::HedgingPosition;
+
+// Triggering settlement doesn't call the oracles, so an unresponsive one can't block it.
+// Each collateral's price is frozen separately afterwards.
+// Settlement pays out in order of priority:
+// 1. stablecoin holders, pro-rata on the value of their stablecoins at the frozen prices
+// 2. hedgers, whose positions are all valued at the frozen price.
+//    The open positions are counted in batches after the price is frozen, so no call loops over all of them.
+//    Their claims are set aside once every position is counted, and they may close their positions at any time after that
+// 3. liquidity providers, pro-rata on whatever is left in the reserves
+#[elrond_wasm::module]
+pub trait GlobalSettlementModule:
+    crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
+    + crate::hedging_agents::HedgingAgentsModule
+    + crate::hedging_token::HedgingTokenModule
+    + crate::liquidity_providers::LiquidityProvidersModule
+    + crate::liquidity_token::LiquidityTokenModule
+    + crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+    + crate::stablecoin_token::StablecoinTokenModule
+    + crate::token_common::TokenCommonModule
+    + crate::twap::TwapModule
+{
+    #[only_owner]
+    #[endpoint(triggerGlobalSettlement)]
+    fn trigger_global_settlement(&self) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
+        // no funding accrues once settlement starts
+        self.update_all_funding_indexes();
+
+        self.global_settlement_active().set(&true);
+        self.settlement_stablecoin_supply()
+            .set(&self.stablecoin_total_circulating_supply().get());
+
+        self.emit_global_settlement_event();
+
+        Ok(())
+    }
+
+    // Anyone may freeze a collateral at its oracle price once settlement is triggered.
+    // Collaterals whose oracle does not respond, is stale or returns a price outside the limits
+    // have to be frozen by the owner through setSettlementPrice
+    #[endpoint(freezeSettlementPrice)]
+    fn freeze_settlement_price(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        require!(
+            self.settlement_price(&collateral_id).is_empty(),
+            "Settlement price already set"
+        );
+
+        let settlement_price = self.try_get_collateral_value_in_dollars(&collateral_id)?;
+        self.require_valid_price(&collateral_id, &settlement_price)?;
+
+        self.freeze_collateral(&collateral_id, settlement_price);
+
+        Ok(())
+    }
+
+    #[only_owner]
+    #[endpoint(setSettlementPrice)]
+    fn set_settlement_price(
+        &self,
+        collateral_id: TokenIdentifier,
+        settlement_price: BigUint,
+    ) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        require!(
+            self.settlement_price(&collateral_id).is_empty(),
+            "Settlement price already set"
+        );
+        require!(settlement_price > 0, "Invalid settlement price");
+
+        self.freeze_collateral(&collateral_id, settlement_price);
+
+        Ok(())
+    }
+
+    // positions that are not open for the collateral, or were already counted, are skipped
+    #[endpoint(countSettlementHedgingPositions)]
+    fn count_settlement_hedging_positions_endpoint(
+        &self,
+        collateral_id: TokenIdentifier,
+        #[var_args] nft_nonces: VarArgs<u64>,
+    ) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        require!(
+            !self.settlement_price(&collateral_id).is_empty(),
+            "Settlement price not set for collateral"
+        );
+        require!(
+            !self.is_settlement_hedgers_claim_final(&collateral_id),
+            "All positions already counted"
+        );
+
+        self.count_settlement_hedging_positions(&collateral_id, nft_nonces.into_vec());
+
+        Ok(())
+    }
+
+    #[payable("*")]
+    #[endpoint(settlementRedeemStablecoins)]
+    fn settlement_redeem_stablecoins(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_amount] payment_amount: BigUint,
+    ) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        require!(
+            payment_token == self.stablecoin_token_id().get(),
+            "May only pay with stablecoins"
+        );
+        self.require_all_settlement_prices_set()?;
+
+        let claims = self.calculate_stablecoin_settlement_claims(&payment_amount);
+        self.burn_stablecoin(&payment_amount);
+
+        let caller = self.blockchain().get_caller();
+        for (collateral_id, collateral_amount) in claims {
+            if collateral_amount > 0 {
+                self.send()
+                    .direct(&caller, &collateral_id, 0, &collateral_amount, &[]);
+            }
+
+            self.emit_settlement_redeem_stablecoins_event(
+                &collateral_id,
+                &payment_amount,
+                &collateral_amount,
+            );
+        }
+
+        Ok(())
+    }
+
+    #[payable("*")]
+    #[endpoint(settlementCloseHedgingPosition)]
+    fn settlement_close_hedging_position(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+    ) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        require!(
+            payment_token == self.hedging_token_id().get(),
+            "May only pay with Hedging NFT"
+        );
+
+        self.settlement_close_position(payment_nonce)
+    }
+
+    #[endpoint(settlementCloseDepositedHedgingPosition)]
+    fn settlement_close_deposited_hedging_position(&self, nft_nonce: u64) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        self.require_deposited_hedging_token_owner(nft_nonce)?;
+
+        self.settlement_close_position(nft_nonce)
+    }
+
+    #[payable("*")]
+    #[endpoint(settlementRedeemLiquidity)]
+    fn settlement_redeem_liquidity(
+        &self,
+        #[payment_token] payment_token: TokenIdentifier,
+        #[payment_nonce] payment_nonce: u64,
+        #[payment_amount] payment_amount: BigUint,
+    ) -> SCResult<()> {
+        self.require_global_settlement_active()?;
+        require!(
+            payment_token == self.liquidity_token_id().get(),
+            "May only pay with liquidity SFTs"
+        );
+
+        let collateral_id = self.collateral_for_liq_sft_nonce(payment_nonce).get();
+        self.require_settlement_hedgers_claim_final(&collateral_id)?;
+
+        // reserves are split between the liquidity tokens in circulation when the first one is redeemed
+        if self
+            .settlement_liq_providers_claim(&collateral_id)
+            .is_empty()
+        {
+            self.settlement_liq_providers_claim(&collateral_id)
+                .set(&self.get_pool_reserves(&collateral_id));
+            self.settlement_liq_tokens_supply(&collateral_id)
+                .set(&self.liq_token_amount_in_circulation(payment_nonce).get());
+        }
+
+        let collateral_amount =
+            self.calculate_liquidity_settlement_claim(&collateral_id, &payment_amount);
+        self.update_pool(&collateral_id, |pool| {
+            pool.collateral_reserves -= &collateral_amount;
+        });
+        self.burn_liq_tokens(payment_nonce, &payment_amount);
+
+        let caller = self.blockchain().get_caller();
+        if collateral_amount > 0 {
+            self.send()
+                .direct(&caller, &collateral_id, 0, &collateral_amount, &[]);
+        }
+
+        self.emit_settlement_redeem_liquidity_event(
+            &collateral_id,
+            &payment_amount,
+            &collateral_amount,
+        );
+
+        Ok(())
+    }
+
+    #[view(getStablecoinSettlementClaim)]
+    fn get_stablecoin_settlement_claim(
+        &self,
+        stablecoin_amount: BigUint,
+    ) -> MultiResultVec<MultiResult2<TokenIdentifier, BigUint>> {
+        self.calculate_stablecoin_settlement_claims(&stablecoin_amount)
+            .into_iter()
+            .map(|claim| claim.into())
+            .collect()
+    }
+
+    #[view(getHedgingPositionSettlementClaim)]
+    fn get_hedging_position_settlement_claim(&self, nft_nonce: u64) -> SCResult<BigUint> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        let hedging_position = self.hedging_position(nft_nonce).get();
+        self.require_settlement_hedgers_claim_final(&hedging_position.collateral_id)?;
+
+        Ok(self.calculate_hedger_settlement_payout(hedging_position))
+    }
+
+    #[view(getLiquiditySettlementClaim)]
+    fn get_liquidity_settlement_claim(
+        &self,
+        collateral_id: TokenIdentifier,
+        liq_tokens_amount: BigUint,
+    ) -> BigUint {
+        self.calculate_liquidity_settlement_claim(&collateral_id, &liq_tokens_amount)
+    }
+
+    // private
+
+    // the NFT is either paid or deposited, so it's in the contract either way
+    fn settlement_close_position(&self, nft_nonce: u64) -> SCResult<()> {
+        self.require_not_liquidated(nft_nonce)?;
+
+        let hedging_position = self.hedging_position(nft_nonce).get();
+        let collateral_id = hedging_position.collateral_id.clone();
+        self.require_settlement_hedgers_claim_final(&collateral_id)?;
+
+        if let Some(withdraw_amount) = &hedging_position.withdraw_amount_after_force_close {
+            self.pending_force_close_withdrawals(&collateral_id)
+                .update(|pending| *pending -= withdraw_amount);
+        }
+
+        // the claim is paid out of the amount set aside for hedgers when the price was frozen
+        let collateral_amount = self.calculate_hedger_settlement_payout(hedging_position);
+
+        self.clear_hedging_position(nft_nonce);
+        self.remove_from_position_indexes(nft_nonce, &collateral_id);
+        self.settlement_position_counted(nft_nonce).clear();
+        self.deposited_hedging_token_owner(nft_nonce).clear();
+        self.burn_hedging_token(nft_nonce);
+
+        let caller = self.blockchain().get_caller();
+        if collateral_amount > 0 {
+            self.send()
+                .direct(&caller, &collateral_id, 0, &collateral_amount, &[]);
+        }
+
+        self.emit_settlement_close_hedging_position_event(
+            nft_nonce,
+            &collateral_id,
+            &collateral_amount,
+        );
+
+        Ok(())
+    }
+
+    fn freeze_collateral(&self, collateral_id: &TokenIdentifier, settlement_price: BigUint) {
+        let accumulated_fees = self.accumulated_tx_fees(collateral_id).get();
+        self.accumulated_tx_fees(collateral_id).clear();
+
+        let stablecoin_claim = self.update_pool(collateral_id, |pool| {
+            let total_collateral =
+                &pool.collateral_amount + &pool.collateral_reserves + &accumulated_fees;
+            let stablecoins_value_in_collateral = self.get_stablecoin_value_in_collateral(
+                collateral_id,
+                &pool.stablecoin_amount,
+                &settlement_price,
+            );
+            let stablecoin_claim = if stablecoins_value_in_collateral <= total_collateral {
+                stablecoins_value_in_collateral
+            } else {
+                total_collateral.clone()
+            };
+
+            pool.collateral_reserves = total_collateral - &stablecoin_claim;
+            pool.collateral_amount = BigUint::zero();
+            pool.stablecoin_amount = BigUint::zero();
+
+            stablecoin_claim
+        });
+
+        self.settlement_price(collateral_id).set(&settlement_price);
+        self.settlement_stablecoin_claim(collateral_id)
+            .set(&stablecoin_claim);
+
+        // the force-closed positions' claims are already known
+        self.settlement_hedgers_claim_total(collateral_id)
+            .set(&self.pending_force_close_withdrawals(collateral_id).get());
+        self.settlement_positions_left_to_count(collateral_id)
+            .set(&self.open_position_nonces(collateral_id).len());
+
+        self.emit_freeze_settlement_price_event(
+            collateral_id,
+            &settlement_price,
+            &stablecoin_claim,
+        );
+
+        if self.is_settlement_hedgers_claim_final(collateral_id) {
+            self.set_aside_settlement_hedgers_claim(collateral_id);
+        }
+    }
+
+    fn count_settlement_hedging_positions(
+        &self,
+        collateral_id: &TokenIdentifier,
+        nft_nonces: Vec<u64>,
+    ) {
+        let settlement_price = self.settlement_price(collateral_id).get();
+        let open_position_nonces = self.open_position_nonces(collateral_id);
+
+        let mut counted_claim = BigUint::zero();
+        let mut counted_positions = 0;
+        for nft_nonce in nft_nonces {
+            if !open_position_nonces.contains(&nft_nonce)
+                || self.settlement_position_counted(nft_nonce).get()
+            {
+                continue;
+            }
+
+            let hedging_position = self.hedging_position(nft_nonce).get();
+            counted_claim +=
+                &self.calculate_hedging_position_claim(hedging_position, &settlement_price);
+            counted_positions += 1;
+            self.settlement_position_counted(nft_nonce).set(&true);
+        }
+
+        self.settlement_hedgers_claim_total(collateral_id)
+            .update(|total| *total += &counted_claim);
+        self.settlement_positions_left_to_count(collateral_id)
+            .update(|left| *left -= counted_positions);
+
+        if self.is_settlement_hedgers_claim_final(collateral_id) {
+            self.set_aside_settlement_hedgers_claim(collateral_id);
+        }
+    }
+
+    // hedgers are paid pro-rata on what is left if the reserves can't cover all of them
+    fn set_aside_settlement_hedgers_claim(&self, collateral_id: &TokenIdentifier) {
+        let hedgers_claim_total = self.settlement_hedgers_claim_total(collateral_id).get();
+        let hedgers_claim = self.update_pool(collateral_id, |pool| {
+            let hedgers_claim = if hedgers_claim_total <= pool.collateral_reserves {
+                hedgers_claim_total.clone()
+            } else {
+                pool.collateral_reserves.clone()
+            };
+            pool.collateral_reserves -= &hedgers_claim;
+
+            hedgers_claim
+        });
+
+        self.settlement_hedgers_claim(collateral_id)
+            .set(&hedgers_claim);
+
+        self.emit_settlement_hedgers_claim_event(
+            collateral_id,
+            &hedgers_claim,
+            &hedgers_claim_total,
+        );
+    }
+
+    fn calculate_stablecoin_settlement_claims(
+        &self,
+        stablecoin_amount: &BigUint,
+    ) -> Vec<(TokenIdentifier, BigUint)> {
+        let stablecoin_supply = self.settlement_stablecoin_supply().get();
+        if stablecoin_supply == 0 {
+            return Vec::new();
+        }
+
+        self.whitelisted_collaterals()
+            .iter()
+            .map(|collateral_id| {
+                let total_claim = self.settlement_stablecoin_claim(&collateral_id).get();
+                let claim = &(stablecoin_amount * &total_claim) / &stablecoin_supply;
+
+                (collateral_id, claim)
+            })
+            .collect()
+    }
+
+    fn calculate_hedger_settlement_payout(
+        &self,
+        hedging_position: HedgingPosition<Self::Api>,
+    ) -> BigUint {
+        let collateral_id = hedging_position.collateral_id.clone();
+        let hedgers_claim_total = self.settlement_hedgers_claim_total(&collateral_id).get();
+        if hedgers_claim_total == 0 {
+            return BigUint::zero();
+        }
+
+        let settlement_price = self.settlement_price(&collateral_id).get();
+        let claim_amount =
+            self.calculate_hedging_position_claim(hedging_position, &settlement_price);
+        let hedgers_claim = self.settlement_hedgers_claim(&collateral_id).get();
+
+        &(&claim_amount * &hedgers_claim) / &hedgers_claim_total
+    }
+
+    fn calculate_liquidity_settlement_claim(
+        &self,
+        collateral_id: &TokenIdentifier,
+        liq_tokens_amount: &BigUint,
+    ) -> BigUint {
+        let (total_claim, liq_tokens_supply) = if self
+            .settlement_liq_providers_claim(collateral_id)
+            .is_empty()
+        {
+            let sft_nonce = self.liq_sft_nonce_for_collateral(collateral_id).get();
+            (
+                self.get_pool_reserves(collateral_id),
+                self.liq_token_amount_in_circulation(sft_nonce).get(),
+            )
+        } else {
+            (
+                self.settlement_liq_providers_claim(collateral_id).get(),
+                self.settlement_liq_tokens_supply(collateral_id).get(),
+            )
+        };
+
+        if liq_tokens_supply == 0 {
+            return BigUint::zero();
+        }
+
+        &(liq_tokens_amount * &total_claim) / &liq_tokens_supply
+    }
+
+    fn require_global_settlement_active(&self) -> SCResult<()> {
+        require!(
+            self.global_settlement_active().get(),
+            "Global settlement is not active"
+        );
+        Ok(())
+    }
+
+    fn is_settlement_hedgers_claim_final(&self, collateral_id: &TokenIdentifier) -> bool {
+        !self.settlement_price(collateral_id).is_empty()
+            && self.settlement_positions_left_to_count(collateral_id).get() == 0
+    }
+
+    fn require_settlement_hedgers_claim_final(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SCResult<()> {
+        require!(
+            !self.settlement_price(collateral_id).is_empty(),
+            "Settlement price not set for collateral"
+        );
+        require!(
+            self.is_settlement_hedgers_claim_final(collateral_id),
+            "Not all positions are counted for settlement"
+        );
+        Ok(())
+    }
+
+    fn require_all_settlement_prices_set(&self) -> SCResult<()> {
+        for collateral_id in self.whitelisted_collaterals().iter() {
+            require!(
+                !self.settlement_price(&collateral_id).is_empty(),
+                "Not all settlement prices are set"
+            );
+        }
+        Ok(())
+    }
+
+    // storage
+
+    #[view(getSettlementPrice)]
+    #[storage_mapper("settlementPrice")]
+    fn settlement_price(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
+    #[view(getSettlementStablecoinSupply)]
+    #[storage_mapper("settlementStablecoinSupply")]
+    fn settlement_stablecoin_supply(&self) -> SingleValueMapper<BigUint>;
+
+    #[view(getSettlementStablecoinClaim)]
+    #[storage_mapper("settlementStablecoinClaim")]
+    fn settlement_stablecoin_claim(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[view(getSettlementHedgersClaim)]
+    #[storage_mapper("settlementHedgersClaim")]
+    fn settlement_hedgers_claim(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    // the sum of the hedgers' claims at the frozen price, which may be over what was set aside for them.
+    // Only includes the positions counted so far until all of them are
+    #[storage_mapper("settlementHedgersClaimTotal")]
+    fn settlement_hedgers_claim_total(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[view(getSettlementPositionsLeftToCount)]
+    #[storage_mapper("settlementPositionsLeftToCount")]
+    fn settlement_positions_left_to_count(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<usize>;
+
+    #[storage_mapper("settlementPositionCounted")]
+    fn settlement_position_counted(&self, nft_nonce: u64) -> SingleValueMapper<bool>;
+
+    #[storage_mapper("settlementLiqProvidersClaim")]
+    fn settlement_liq_providers_claim(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+
+    #[storage_mapper("settlementLiqTokensSupply")]
+    fn settlement_liq_tokens_supply(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+}
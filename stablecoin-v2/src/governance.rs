@@ -0,0 +1,858 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::{
+    fees::FeeCurveType,
+    hedging_agents::ForceCloseOrdering,
+    keepers::KeeperAction,
+    math::PERCENTAGE_PRECISION,
+    price_sources::{PriceSource, PriceSourceType},
+    twap::PricedOperation,
+};
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum GovernedParameter {
+    TargetHedgingRatio,
+    HedgingRatioLimit,
+    MinHedgingPeriodSeconds,
+    GovernanceDelaySeconds,
+    MaxLeverage,
+    FeeCurve,
+    HedgingMaintenanceRatio,
+    LiqProviderFeeRewardPercentage,
+    MaxPriceAgeSeconds,
+    MaxPriceDeviationPercentage,
+    TwapWindowSeconds,
+    UseTwapPrice,
+    CloseOrderBountyPercentage,
+    LiquidationBountyPercentage,
+    ForceCloseOrdering,
+    MaxFundingRatePerSecond,
+    MaxHedgingPeriodSeconds,
+    KeeperReward,
+    TreasuryFeePercentage,
+    AddPriceSource,
+    RemovePriceSource,
+    PriceSourcesQuorum,
+    RemoveCollateral,
+    TreasuryAddress,
+    ClearLastAcceptedPrice,
+}
+
+/// collateral_id is None for global parameters.
+/// values holds a single entry, except for UseTwapPrice, which holds the operation index and the 0/1 flag,
+/// KeeperReward, which holds the action index, the min interval and the reward amount,
+/// FeeCurve, which holds the curve type index followed by the flattened (hedging ratio, fee) breakpoints,
+/// AddPriceSource, which holds the source type index and the DEX pair's quote decimals,
+/// and RemoveCollateral, TreasuryAddress and ClearLastAcceptedPrice, which hold no values.
+/// address is only set for the price source and treasury address proposals.
+#[derive(TypeAbi, TopEncode, TopDecode)]
+pub struct Proposal<M: ManagedTypeApi> {
+    pub id: u64,
+    pub parameter: GovernedParameter,
+    pub collateral_id: Option<TokenIdentifier<M>>,
+    pub values: Vec<BigUint<M>>,
+    pub address: Option<ManagedAddress<M>>,
+    pub proposal_timestamp: u64,
+    pub executable_after_timestamp: u64,
+}
+
+#[elrond_wasm::module]
+pub trait GovernanceModule:
+    crate::config::ConfigModule
+    + crate::events::EventsModule
+    + crate::fees::FeesModule
+    + crate::funding::FundingModule
+    + crate::hedging_agents::HedgingAgentsModule
+    + crate::hedging_token::HedgingTokenModule
+    + crate::keepers::KeepersModule
+    + crate::liquidity_providers::LiquidityProvidersModule
+    + crate::liquidity_token::LiquidityTokenModule
+    + crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+    + crate::token_common::TokenCommonModule
+    + crate::treasury::TreasuryModule
+    + crate::twap::TwapModule
+{
+    #[only_owner]
+    #[endpoint(proposeTargetHedgingRatio)]
+    fn propose_target_hedging_ratio(&self, target_hedging_ratio: BigUint) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::TargetHedgingRatio,
+            None,
+            Vec::from([target_hedging_ratio]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeHedgingRatioLimit)]
+    fn propose_hedging_ratio_limit(&self, hedging_ratio_limit: BigUint) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::HedgingRatioLimit,
+            None,
+            Vec::from([hedging_ratio_limit]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMinHedgingPeriodSeconds)]
+    fn propose_min_hedging_period_seconds(&self, min_hedging_period_seconds: u64) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MinHedgingPeriodSeconds,
+            None,
+            Vec::from([BigUint::from(min_hedging_period_seconds)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMaxHedgingPeriodSeconds)]
+    fn propose_max_hedging_period_seconds(
+        &self,
+        collateral_id: TokenIdentifier,
+        max_hedging_period_seconds: u64,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MaxHedgingPeriodSeconds,
+            Some(collateral_id),
+            Vec::from([BigUint::from(max_hedging_period_seconds)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeGovernanceDelaySeconds)]
+    fn propose_governance_delay_seconds(&self, governance_delay_seconds: u64) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::GovernanceDelaySeconds,
+            None,
+            Vec::from([BigUint::from(governance_delay_seconds)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMaxLeverage)]
+    fn propose_max_leverage(
+        &self,
+        collateral_id: TokenIdentifier,
+        max_leverage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MaxLeverage,
+            Some(collateral_id),
+            Vec::from([max_leverage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeFeeCurve)]
+    fn propose_fee_curve(
+        &self,
+        collateral_id: TokenIdentifier,
+        curve_type: FeeCurveType,
+        #[var_args] breakpoints: VarArgs<MultiArg2<BigUint, BigUint>>,
+    ) -> SCResult<u64> {
+        let mut values = Vec::from([BigUint::from(curve_type as u64)]);
+        for breakpoint in breakpoints.into_vec() {
+            let (hedging_ratio, fee_percentage) = breakpoint.into_tuple();
+            values.push(hedging_ratio);
+            values.push(fee_percentage);
+        }
+
+        self.create_proposal(GovernedParameter::FeeCurve, Some(collateral_id), values)
+    }
+
+    #[only_owner]
+    #[endpoint(proposeHedgingMaintenanceRatio)]
+    fn propose_hedging_maintenance_ratio(
+        &self,
+        collateral_id: TokenIdentifier,
+        hedging_maintenance_ratio: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::HedgingMaintenanceRatio,
+            Some(collateral_id),
+            Vec::from([hedging_maintenance_ratio]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeCloseOrderBountyPercentage)]
+    fn propose_close_order_bounty_percentage(
+        &self,
+        collateral_id: TokenIdentifier,
+        close_order_bounty_percentage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::CloseOrderBountyPercentage,
+            Some(collateral_id),
+            Vec::from([close_order_bounty_percentage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeLiquidationBountyPercentage)]
+    fn propose_liquidation_bounty_percentage(
+        &self,
+        collateral_id: TokenIdentifier,
+        liquidation_bounty_percentage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::LiquidationBountyPercentage,
+            Some(collateral_id),
+            Vec::from([liquidation_bounty_percentage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeForceCloseOrdering)]
+    fn propose_force_close_ordering(
+        &self,
+        collateral_id: TokenIdentifier,
+        ordering: ForceCloseOrdering,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::ForceCloseOrdering,
+            Some(collateral_id),
+            Vec::from([BigUint::from(ordering as u64)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMaxFundingRatePerSecond)]
+    fn propose_max_funding_rate_per_second(
+        &self,
+        collateral_id: TokenIdentifier,
+        max_funding_rate_per_second: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MaxFundingRatePerSecond,
+            Some(collateral_id),
+            Vec::from([max_funding_rate_per_second]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeLiqProviderFeeRewardPercentage)]
+    fn propose_liq_provider_fee_reward_percentage(
+        &self,
+        collateral_id: TokenIdentifier,
+        liq_provider_fee_reward_percentage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::LiqProviderFeeRewardPercentage,
+            Some(collateral_id),
+            Vec::from([liq_provider_fee_reward_percentage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMaxPriceAgeSeconds)]
+    fn propose_max_price_age_seconds(
+        &self,
+        collateral_id: TokenIdentifier,
+        max_price_age_seconds: u64,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MaxPriceAgeSeconds,
+            Some(collateral_id),
+            Vec::from([BigUint::from(max_price_age_seconds)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeMaxPriceDeviationPercentage)]
+    fn propose_max_price_deviation_percentage(
+        &self,
+        collateral_id: TokenIdentifier,
+        max_price_deviation_percentage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::MaxPriceDeviationPercentage,
+            Some(collateral_id),
+            Vec::from([max_price_deviation_percentage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeTwapWindowSeconds)]
+    fn propose_twap_window_seconds(
+        &self,
+        collateral_id: TokenIdentifier,
+        twap_window_seconds: u64,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::TwapWindowSeconds,
+            Some(collateral_id),
+            Vec::from([BigUint::from(twap_window_seconds)]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeUseTwapPrice)]
+    fn propose_use_twap_price(
+        &self,
+        collateral_id: TokenIdentifier,
+        operation: PricedOperation,
+        use_twap: bool,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::UseTwapPrice,
+            Some(collateral_id),
+            Vec::from([
+                BigUint::from(operation as u64),
+                BigUint::from(use_twap as u64),
+            ]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeKeeperReward)]
+    fn propose_keeper_reward(
+        &self,
+        collateral_id: TokenIdentifier,
+        action: KeeperAction,
+        min_interval_seconds: u64,
+        reward_amount: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::KeeperReward,
+            Some(collateral_id),
+            Vec::from([
+                BigUint::from(action as u64),
+                BigUint::from(min_interval_seconds),
+                reward_amount,
+            ]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeTreasuryFeePercentage)]
+    fn propose_treasury_fee_percentage(
+        &self,
+        collateral_id: TokenIdentifier,
+        treasury_fee_percentage: BigUint,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::TreasuryFeePercentage,
+            Some(collateral_id),
+            Vec::from([treasury_fee_percentage]),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeTreasuryAddress)]
+    fn propose_treasury_address(&self, treasury_address: ManagedAddress) -> SCResult<u64> {
+        self.create_proposal_with_address(
+            GovernedParameter::TreasuryAddress,
+            None,
+            Vec::new(),
+            Some(treasury_address),
+        )
+    }
+
+    // quote_num_decimals is ignored for price aggregators, which report the decimals of their feeds
+    #[only_owner]
+    #[endpoint(proposeAddPriceSource)]
+    fn propose_add_price_source(
+        &self,
+        collateral_id: TokenIdentifier,
+        source_type: PriceSourceType,
+        address: ManagedAddress,
+        quote_num_decimals: u32,
+    ) -> SCResult<u64> {
+        self.create_proposal_with_address(
+            GovernedParameter::AddPriceSource,
+            Some(collateral_id),
+            Vec::from([
+                BigUint::from(source_type as u64),
+                BigUint::from(quote_num_decimals),
+            ]),
+            Some(address),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposeRemovePriceSource)]
+    fn propose_remove_price_source(
+        &self,
+        collateral_id: TokenIdentifier,
+        source_type: PriceSourceType,
+        address: ManagedAddress,
+    ) -> SCResult<u64> {
+        self.create_proposal_with_address(
+            GovernedParameter::RemovePriceSource,
+            Some(collateral_id),
+            Vec::from([BigUint::from(source_type as u64)]),
+            Some(address),
+        )
+    }
+
+    #[only_owner]
+    #[endpoint(proposePriceSourcesQuorum)]
+    fn propose_price_sources_quorum(
+        &self,
+        collateral_id: TokenIdentifier,
+        quorum: usize,
+    ) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::PriceSourcesQuorum,
+            Some(collateral_id),
+            Vec::from([BigUint::from(quorum as u64)]),
+        )
+    }
+
+    // The collateral's parameters are cleared, and may only be set again by whitelisting it anew.
+    // The removal is timelocked like the parameter changes, so it can't be used to skip their delay
+    #[only_owner]
+    #[endpoint(proposeRemoveCollateral)]
+    fn propose_remove_collateral(&self, collateral_id: TokenIdentifier) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::RemoveCollateral,
+            Some(collateral_id),
+            Vec::new(),
+        )
+    }
+
+    // lifts the deviation check until the next accepted price, so it's timelocked like the check's parameters
+    #[only_owner]
+    #[endpoint(proposeClearLastAcceptedPrice)]
+    fn propose_clear_last_accepted_price(&self, collateral_id: TokenIdentifier) -> SCResult<u64> {
+        self.create_proposal(
+            GovernedParameter::ClearLastAcceptedPrice,
+            Some(collateral_id),
+            Vec::new(),
+        )
+    }
+
+    // anyone may execute a proposal once its delay has passed
+    #[endpoint(executeProposal)]
+    fn execute_proposal(&self, proposal_id: u64) -> SCResult<()> {
+        self.require_pending_proposal(proposal_id)?;
+
+        let proposal = self.proposal(proposal_id).get();
+        let current_time = self.blockchain().get_block_timestamp();
+        require!(
+            current_time >= proposal.executable_after_timestamp,
+            "Proposal delay has not passed yet"
+        );
+
+        // state might have changed since the proposal was created
+        self.require_valid_proposal(&proposal)?;
+        self.apply_proposal(&proposal);
+
+        self.pending_proposal_ids().remove(&proposal_id);
+        self.proposal(proposal_id).clear();
+
+        self.emit_execute_proposal_event(&proposal);
+
+        Ok(())
+    }
+
+    #[only_owner]
+    #[endpoint(cancelProposal)]
+    fn cancel_proposal(&self, proposal_id: u64) -> SCResult<()> {
+        self.require_pending_proposal(proposal_id)?;
+
+        let proposal = self.proposal(proposal_id).get();
+        self.pending_proposal_ids().remove(&proposal_id);
+        self.proposal(proposal_id).clear();
+
+        self.emit_cancel_proposal_event(&proposal);
+
+        Ok(())
+    }
+
+    #[view(getProposal)]
+    fn get_proposal(&self, proposal_id: u64) -> SCResult<Proposal<Self::Api>> {
+        self.require_pending_proposal(proposal_id)?;
+
+        Ok(self.proposal(proposal_id).get())
+    }
+
+    #[view(getPendingProposals)]
+    fn get_pending_proposals(&self) -> MultiResultVec<Proposal<Self::Api>> {
+        self.pending_proposal_ids()
+            .iter()
+            .map(|proposal_id| self.proposal(proposal_id).get())
+            .collect()
+    }
+
+    // private
+
+    fn create_proposal(
+        &self,
+        parameter: GovernedParameter,
+        collateral_id: Option<TokenIdentifier>,
+        values: Vec<BigUint>,
+    ) -> SCResult<u64> {
+        self.create_proposal_with_address(parameter, collateral_id, values, None)
+    }
+
+    fn create_proposal_with_address(
+        &self,
+        parameter: GovernedParameter,
+        collateral_id: Option<TokenIdentifier>,
+        values: Vec<BigUint>,
+        address: Option<ManagedAddress>,
+    ) -> SCResult<u64> {
+        let proposal_id = self.last_proposal_id().get() + 1;
+        let proposal_timestamp = self.blockchain().get_block_timestamp();
+        let governance_delay_seconds = self.governance_delay_seconds().get();
+
+        let proposal = Proposal {
+            id: proposal_id,
+            parameter,
+            collateral_id,
+            values,
+            address,
+            proposal_timestamp,
+            executable_after_timestamp: proposal_timestamp + governance_delay_seconds,
+        };
+        self.require_valid_proposal(&proposal)?;
+
+        self.last_proposal_id().set(&proposal_id);
+        self.proposal(proposal_id).set(&proposal);
+        self.pending_proposal_ids().insert(proposal_id);
+
+        self.emit_propose_event(&proposal);
+
+        Ok(proposal_id)
+    }
+
+    fn require_valid_proposal(&self, proposal: &Proposal<Self::Api>) -> SCResult<()> {
+        if let Some(collateral_id) = &proposal.collateral_id {
+            self.require_collateral_in_whitelist(collateral_id)?;
+        }
+
+        let values = &proposal.values;
+        match proposal.parameter {
+            GovernedParameter::TargetHedgingRatio => {
+                let hedging_ratio_limit = self.hedging_ratio_limit().get();
+                require!(
+                    values[0] <= hedging_ratio_limit,
+                    "Target hedging ratio may not be over the limit"
+                );
+            }
+            GovernedParameter::HedgingRatioLimit => {
+                let target_hedging_ratio = self.target_hedging_ratio().get();
+                require!(
+                    values[0] >= target_hedging_ratio,
+                    "Hedging ratio limit may not be under the target"
+                );
+            }
+            GovernedParameter::MinHedgingPeriodSeconds
+            | GovernedParameter::GovernanceDelaySeconds
+            | GovernedParameter::MaxPriceAgeSeconds
+            | GovernedParameter::MaxHedgingPeriodSeconds => {
+                require!(values[0].to_u64().is_some(), "Invalid number of seconds");
+            }
+            GovernedParameter::TwapWindowSeconds => {
+                self.require_valid_twap_window(&values[0])?;
+            }
+            GovernedParameter::FeeCurve => {
+                let curve_type = match values[0].to_u64().and_then(FeeCurveType::from_index) {
+                    Some(curve_type) => curve_type,
+                    None => return sc_error!("Invalid fee curve type"),
+                };
+                require!(values.len() % 2 == 1, "Invalid fee curve breakpoints");
+
+                let curve = self.fee_curve_from_values(&values[1..]);
+                self.require_valid_fee_curve(curve_type, &curve)?;
+            }
+            GovernedParameter::LiqProviderFeeRewardPercentage => {
+                self.require_valid_liq_provider_fee_reward_percentage(&values[0])?;
+            }
+            GovernedParameter::CloseOrderBountyPercentage => {
+                require!(
+                    values[0] <= PERCENTAGE_PRECISION,
+                    "Invalid close order bounty percentage"
+                );
+            }
+            GovernedParameter::ForceCloseOrdering => {
+                let is_valid_ordering = values[0]
+                    .to_u64()
+                    .and_then(ForceCloseOrdering::from_index)
+                    .is_some();
+                require!(is_valid_ordering, "Invalid force close ordering");
+            }
+            GovernedParameter::MaxFundingRatePerSecond => {
+                require!(
+                    values[0] <= PERCENTAGE_PRECISION,
+                    "Invalid max funding rate"
+                );
+            }
+            GovernedParameter::TreasuryFeePercentage => {
+                require!(
+                    values[0] <= PERCENTAGE_PRECISION,
+                    "Invalid treasury fee percentage"
+                );
+            }
+            GovernedParameter::LiquidationBountyPercentage => {
+                require!(
+                    values[0] <= PERCENTAGE_PRECISION,
+                    "Invalid liquidation bounty percentage"
+                );
+            }
+            GovernedParameter::MaxPriceDeviationPercentage => {
+                require!(
+                    values[0] <= PERCENTAGE_PRECISION,
+                    "Invalid price deviation percentage"
+                );
+            }
+            GovernedParameter::UseTwapPrice => {
+                let is_valid_operation = values[0]
+                    .to_u64()
+                    .and_then(PricedOperation::from_index)
+                    .is_some();
+                require!(is_valid_operation, "Invalid priced operation");
+                require!(values[1] <= 1u64, "Invalid TWAP flag");
+            }
+            GovernedParameter::KeeperReward => {
+                let is_valid_action = values[0]
+                    .to_u64()
+                    .and_then(KeeperAction::from_index)
+                    .is_some();
+                require!(is_valid_action, "Invalid keeper action");
+                require!(values[1].to_u64().is_some(), "Invalid min interval");
+            }
+            GovernedParameter::MaxLeverage => {
+                self.require_valid_max_leverage(&values[0])?;
+            }
+            GovernedParameter::AddPriceSource => {
+                let (collateral_id, source) = self.get_proposal_price_source(proposal)?;
+                require!(
+                    self.blockchain().is_smart_contract(&source.address),
+                    "Price source address is not a smart contract"
+                );
+                require!(
+                    !self.price_sources(&collateral_id).contains(&source),
+                    "Price source already added"
+                );
+                require!(values[1].to_u64().is_some(), "Invalid quote decimals");
+
+                let mut number_of_dex_sources =
+                    self.get_number_of_dex_price_sources(&collateral_id);
+                if source.source_type == PriceSourceType::DexPair {
+                    number_of_dex_sources += 1;
+                }
+                let quorum = self.price_sources_quorum(&collateral_id).get();
+                self.require_valid_dex_price_sources_quorum(quorum, number_of_dex_sources)?;
+            }
+            GovernedParameter::RemovePriceSource => {
+                let (collateral_id, source) = self.get_proposal_price_source(proposal)?;
+                let price_sources = self.price_sources(&collateral_id);
+                require!(price_sources.contains(&source), "Unknown price source");
+
+                let quorum = self.price_sources_quorum(&collateral_id).get();
+                self.require_valid_price_sources_quorum(quorum, price_sources.len() - 1)?;
+            }
+            GovernedParameter::PriceSourcesQuorum => {
+                let (collateral_id, quorum) = match (&proposal.collateral_id, values[0].to_u64()) {
+                    (Some(collateral_id), Some(quorum)) => (collateral_id, quorum as usize),
+                    _ => return sc_error!("Invalid quorum"),
+                };
+                let number_of_sources = self.price_sources(collateral_id).len();
+                self.require_valid_price_sources_quorum(quorum, number_of_sources)?;
+
+                let number_of_dex_sources = self.get_number_of_dex_price_sources(collateral_id);
+                self.require_valid_dex_price_sources_quorum(quorum, number_of_dex_sources)?;
+            }
+            GovernedParameter::HedgingMaintenanceRatio => {
+                self.require_valid_hedging_maintenance_ratio(&values[0])?;
+            }
+            GovernedParameter::RemoveCollateral => {
+                self.require_no_global_settlement()?;
+            }
+            GovernedParameter::TreasuryAddress => {
+                require!(proposal.address.is_some(), "Invalid treasury address");
+            }
+            GovernedParameter::ClearLastAcceptedPrice => {}
+        }
+
+        Ok(())
+    }
+
+    fn apply_proposal(&self, proposal: &Proposal<Self::Api>) {
+        let values = &proposal.values;
+        match (&proposal.collateral_id, proposal.parameter) {
+            (None, GovernedParameter::TargetHedgingRatio) => {
+                // the funding rate depends on the target, so the pending funding is accrued at the old one
+                self.update_all_funding_indexes();
+                self.target_hedging_ratio().set(&values[0]);
+            }
+            (None, GovernedParameter::HedgingRatioLimit) => {
+                self.update_all_funding_indexes();
+                self.hedging_ratio_limit().set(&values[0]);
+            }
+            (None, GovernedParameter::MinHedgingPeriodSeconds) => {
+                self.min_hedging_period_seconds()
+                    .set(&values[0].to_u64().unwrap_or_default());
+            }
+            (None, GovernedParameter::GovernanceDelaySeconds) => {
+                self.governance_delay_seconds()
+                    .set(&values[0].to_u64().unwrap_or_default());
+            }
+            (Some(collateral_id), GovernedParameter::MaxLeverage) => {
+                self.max_leverage(collateral_id).set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::FeeCurve) => {
+                if let Some(curve_type) = values[0].to_u64().and_then(FeeCurveType::from_index) {
+                    self.fee_curve(collateral_id, curve_type)
+                        .set(&self.fee_curve_from_values(&values[1..]));
+
+                    // the snapshot is only refreshed on hedging ratio changes otherwise
+                    self.update_fee_configuration(collateral_id);
+                }
+            }
+            (Some(collateral_id), GovernedParameter::HedgingMaintenanceRatio) => {
+                self.hedging_maintenance_ratio(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::CloseOrderBountyPercentage) => {
+                self.close_order_bounty_percentage(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::ForceCloseOrdering) => {
+                if let Some(ordering) = values[0].to_u64().and_then(ForceCloseOrdering::from_index)
+                {
+                    self.force_close_ordering(collateral_id).set(&ordering);
+                }
+            }
+            (Some(collateral_id), GovernedParameter::MaxFundingRatePerSecond) => {
+                // the rate may change with funding pending, so the index is brought up to date first
+                self.update_funding_index(collateral_id);
+                self.max_funding_rate_per_second(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::MaxHedgingPeriodSeconds) => {
+                self.max_hedging_period_seconds(collateral_id)
+                    .set(&values[0].to_u64().unwrap_or_default());
+            }
+            (Some(collateral_id), GovernedParameter::TreasuryFeePercentage) => {
+                self.treasury_fee_percentage(collateral_id).set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::LiquidationBountyPercentage) => {
+                self.liquidation_bounty_percentage(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::LiqProviderFeeRewardPercentage) => {
+                self.liq_provider_fee_reward_percentage(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::MaxPriceAgeSeconds) => {
+                self.max_price_age_seconds(collateral_id)
+                    .set(&values[0].to_u64().unwrap_or_default());
+            }
+            (Some(collateral_id), GovernedParameter::MaxPriceDeviationPercentage) => {
+                self.max_price_deviation_percentage(collateral_id)
+                    .set(&values[0]);
+            }
+            (Some(collateral_id), GovernedParameter::TwapWindowSeconds) => {
+                self.twap_window_seconds(collateral_id)
+                    .set(&values[0].to_u64().unwrap_or_default());
+            }
+            (Some(collateral_id), GovernedParameter::UseTwapPrice) => {
+                if let Some(operation) = values[0].to_u64().and_then(PricedOperation::from_index) {
+                    self.use_twap_price(collateral_id, operation)
+                        .set(&(values[1] == 1u64));
+                }
+            }
+            (Some(collateral_id), GovernedParameter::KeeperReward) => {
+                if let Some(action) = values[0].to_u64().and_then(KeeperAction::from_index) {
+                    self.keeper_reward_min_interval_seconds(collateral_id, action)
+                        .set(&values[1].to_u64().unwrap_or_default());
+                    self.keeper_reward_amount(collateral_id, action)
+                        .set(&values[2]);
+                }
+            }
+            (Some(collateral_id), GovernedParameter::AddPriceSource) => {
+                if let Ok((_, source)) = self.get_proposal_price_source(proposal) {
+                    let quote_num_decimals = values[1].to_u64().unwrap_or_default() as u32;
+                    self.add_price_source(collateral_id, source, quote_num_decimals);
+                }
+            }
+            (Some(collateral_id), GovernedParameter::RemovePriceSource) => {
+                if let Ok((_, source)) = self.get_proposal_price_source(proposal) {
+                    self.remove_price_source(collateral_id, &source);
+                }
+            }
+            (Some(collateral_id), GovernedParameter::PriceSourcesQuorum) => {
+                self.price_sources_quorum(collateral_id)
+                    .set(&(values[0].to_u64().unwrap_or_default() as usize));
+            }
+            (Some(collateral_id), GovernedParameter::RemoveCollateral) => {
+                self.remove_collateral(collateral_id);
+            }
+            (Some(collateral_id), GovernedParameter::ClearLastAcceptedPrice) => {
+                self.last_accepted_price(collateral_id).clear();
+            }
+            (None, GovernedParameter::TreasuryAddress) => {
+                if let Some(address) = &proposal.address {
+                    self.treasury_address().set(address);
+                }
+            }
+            // the propose endpoints only create valid parameter and collateral combinations
+            _ => {}
+        }
+    }
+
+    fn get_proposal_price_source(
+        &self,
+        proposal: &Proposal<Self::Api>,
+    ) -> SCResult<(TokenIdentifier, PriceSource<Self::Api>)> {
+        let opt_source_type = proposal.values[0]
+            .to_u64()
+            .and_then(PriceSourceType::from_index);
+        match (&proposal.collateral_id, opt_source_type, &proposal.address) {
+            (Some(collateral_id), Some(source_type), Some(address)) => Ok((
+                collateral_id.clone(),
+                PriceSource {
+                    source_type,
+                    address: address.clone(),
+                },
+            )),
+            _ => sc_error!("Invalid price source"),
+        }
+    }
+
+    // the pool info is kept, in case the collateral is whitelisted again
+    fn remove_collateral(&self, collateral_id: &TokenIdentifier) {
+        self.collateral_ticker(collateral_id).clear();
+        self.collateral_num_decimals(collateral_id).clear();
+        self.max_leverage(collateral_id).clear();
+        for curve_type in FeeCurveType::all().iter() {
+            self.fee_curve(collateral_id, *curve_type).clear();
+        }
+        self.hedging_maintenance_ratio(collateral_id).clear();
+        self.liq_provider_fee_reward_percentage(collateral_id)
+            .clear();
+        self.collateral_whitelisted(collateral_id).clear();
+        self.whitelisted_collaterals().remove(collateral_id);
+    }
+
+    fn require_pending_proposal(&self, proposal_id: u64) -> SCResult<()> {
+        require!(
+            self.pending_proposal_ids().contains(&proposal_id),
+            "Proposal does not exist or is no longer pending"
+        );
+        Ok(())
+    }
+
+    // storage
+
+    #[view(getGovernanceDelaySeconds)]
+    #[storage_mapper("governanceDelaySeconds")]
+    fn governance_delay_seconds(&self) -> SingleValueMapper<u64>;
+
+    #[storage_mapper("lastProposalId")]
+    fn last_proposal_id(&self) -> SingleValueMapper<u64>;
+
+    #[storage_mapper("proposal")]
+    fn proposal(&self, proposal_id: u64) -> SingleValueMapper<Proposal<Self::Api>>;
+
+    #[storage_mapper("pendingProposalIds")]
+    fn pending_proposal_ids(&self) -> SetMapper<u64>;
+}
@@ -3,30 +3,45 @@
 elrond_wasm::imports!();
 elrond_wasm::derive_imports!();
 
-mod actors;
-mod economics;
-mod tokens;
+pub mod actors;
+mod config;
+pub mod economics;
+mod events;
+pub mod global_settlement;
+mod governance;
+pub mod oracle;
+pub mod pause;
+pub mod tokens;
 
 use actors::*;
 use economics::*;
+use oracle::*;
 use tokens::*;
 
-// TODO: Add events
-
 #[elrond_wasm::contract]
 pub trait StablecoinV2:
-    fees::FeesModule
+    config::ConfigModule
+    + events::EventsModule
+    + fees::FeesModule
+    + funding::FundingModule
+    + global_settlement::GlobalSettlementModule
+    + governance::GovernanceModule
     + hedging_agents::HedgingAgentsModule
     + hedging_token::HedgingTokenModule
     + keepers::KeepersModule
     + liquidity_providers::LiquidityProvidersModule
     + liquidity_token::LiquidityTokenModule
     + math::MathModule
+    + pause::PauseModule
     + pools::PoolsModule
     + price_aggregator_proxy::PriceAggregatorModule
+    + price_checks::PriceChecksModule
+    + price_sources::PriceSourcesModule
     + stablecoin_token::StablecoinTokenModule
     + stable_seekers::StableSeekers
     + token_common::TokenCommonModule
+    + treasury::TreasuryModule
+    + twap::TwapModule
 {
     #[init]
     fn init(
@@ -35,6 +50,7 @@ pub trait StablecoinV2:
         min_hedging_period_seconds: u64,
         target_hedging_ratio: BigUint,
         hedging_ratio_limit: BigUint,
+        governance_delay_seconds: u64,
     ) -> SCResult<()> {
         require!(
             self.blockchain()
@@ -49,6 +65,8 @@ pub trait StablecoinV2:
             .set(&min_hedging_period_seconds);
         self.target_hedging_ratio().set(&target_hedging_ratio);
         self.hedging_ratio_limit().set(&hedging_ratio_limit);
+        self.governance_delay_seconds()
+            .set(&governance_delay_seconds);
 
         Ok(())
     }
@@ -68,31 +86,36 @@ pub trait StablecoinV2:
         min_slippage_percentage: BigUint,
         max_slippage_percentage: BigUint,
     ) -> SCResult<()> {
+        self.require_no_global_settlement()?;
+
+        // parameters of listed collaterals may only be changed, and the collaterals removed,
+        // through governance proposals
         require!(
-            min_fees_percentage <= max_fees_percentage
-                && max_fees_percentage < math::PERCENTAGE_PRECISION,
-            "Invalid fees percentages"
-        );
-        require!(
-            min_slippage_percentage <= max_slippage_percentage
-                && max_slippage_percentage < math::PERCENTAGE_PRECISION,
-            "Invalid slippage percentages"
+            !self.is_collateral_whitelisted(&collateral_id),
+            "Collateral already whitelisted"
         );
+        self.set_linear_fee_curves(
+            &collateral_id,
+            &min_fees_percentage,
+            &max_fees_percentage,
+            &min_slippage_percentage,
+            &max_slippage_percentage,
+        )?;
+        self.require_valid_max_leverage(&max_leverage)?;
+        self.require_valid_hedging_maintenance_ratio(&hedging_maintenance_ratio)?;
+        self.require_valid_liq_provider_fee_reward_percentage(&liq_provider_fee_reward_percentage)?;
 
         self.collateral_ticker(&collateral_id)
             .set(&collateral_ticker);
         self.collateral_num_decimals(&collateral_id)
             .set(&collateral_num_decimals);
         self.max_leverage(&collateral_id).set(&max_leverage);
-        self.min_max_fees_percentage(&collateral_id)
-            .set(&(min_fees_percentage, max_fees_percentage));
         self.hedging_maintenance_ratio(&collateral_id)
             .set(&hedging_maintenance_ratio);
         self.liq_provider_fee_reward_percentage(&collateral_id)
             .set(&liq_provider_fee_reward_percentage);
-        self.min_max_slippage_percentage(&collateral_id)
-            .set(&(min_slippage_percentage, max_slippage_percentage));
         self.collateral_whitelisted(&collateral_id).set(&true);
+        self.whitelisted_collaterals().insert(collateral_id.clone());
 
         // preserve the pool info if it was added, removed, and then added again
         self.pool_for_collateral(&collateral_id)
@@ -101,17 +124,21 @@ pub trait StablecoinV2:
         Ok(())
     }
 
+    // collaterals whitelisted before the upgrade are missing from the iterable whitelist,
+    // so they would be skipped by the global settlement and the per-collateral views.
+    // Their min and max fees are also turned into fee curves
     #[only_owner]
-    #[endpoint(removeCollateralFromWhitelist)]
-    fn remove_collateral_from_whitelist(&self, collateral_id: TokenIdentifier) {
-        self.collateral_ticker(&collateral_id).clear();
-        self.collateral_num_decimals(&collateral_id).clear();
-        self.max_leverage(&collateral_id).clear();
-        self.min_max_fees_percentage(&collateral_id).clear();
-        self.hedging_maintenance_ratio(&collateral_id).clear();
-        self.liq_provider_fee_reward_percentage(&collateral_id)
-            .clear();
-        self.min_max_slippage_percentage(&collateral_id).clear();
-        self.collateral_whitelisted(&collateral_id).clear();
+    #[endpoint(migrateCollateralWhitelist)]
+    fn migrate_collateral_whitelist(
+        &self,
+        #[var_args] collateral_ids: VarArgs<TokenIdentifier>,
+    ) -> SCResult<()> {
+        for collateral_id in collateral_ids.into_vec() {
+            self.require_collateral_in_whitelist(&collateral_id)?;
+            self.migrate_legacy_fee_curves(&collateral_id)?;
+            self.whitelisted_collaterals().insert(collateral_id);
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,3 @@
+pub mod price_checks;
+pub mod price_sources;
+pub mod twap;
@@ -0,0 +1,107 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::math::PERCENTAGE_PRECISION;
+
+// the allowed deviation grows by the max deviation for every period elapsed since the last accepted price
+const DEVIATION_SCALING_PERIOD_SECONDS: u64 = 60 * 60;
+
+#[derive(TypeAbi, TopEncode, TopDecode)]
+pub struct AcceptedPrice<M: ManagedTypeApi> {
+    pub price: BigUint<M>,
+    pub timestamp: u64,
+}
+
+#[derive(TypeAbi, TopEncode, TopDecode)]
+pub struct PriceRound {
+    pub round_id: u32,
+    pub first_seen_timestamp: u64,
+}
+
+// The price aggregator proxy does not expose the round timestamp,
+// so a round is considered stale once no new round was seen for longer than the max age.
+// The age is counted from the first time this contract saw the round, not from when the round was submitted,
+// so a round that was already old then is still accepted for up to the max age.
+// The price itself may stay the same between rounds.
+// The last accepted price may only be cleared through governance
+#[elrond_wasm::module]
+pub trait PriceChecksModule: crate::math::MathModule {
+    fn require_valid_price(
+        &self,
+        collateral_id: &TokenIdentifier,
+        price: &BigUint,
+    ) -> SCResult<()> {
+        require!(
+            !self.is_price_deviation_too_high(collateral_id, price),
+            "Oracle price deviates too much from the last accepted price"
+        );
+        Ok(())
+    }
+
+    fn is_round_stale(&self, collateral_id: &TokenIdentifier, round: &PriceRound) -> bool {
+        let max_price_age_seconds = self.max_price_age_seconds(collateral_id).get();
+        if max_price_age_seconds == 0 {
+            return false;
+        }
+
+        let current_time = self.blockchain().get_block_timestamp();
+        current_time - round.first_seen_timestamp > max_price_age_seconds
+    }
+
+    fn is_price_deviation_too_high(
+        &self,
+        collateral_id: &TokenIdentifier,
+        price: &BigUint,
+    ) -> bool {
+        let max_price_deviation_percentage =
+            self.max_price_deviation_percentage(collateral_id).get();
+        if max_price_deviation_percentage == 0 || self.last_accepted_price(collateral_id).is_empty()
+        {
+            return false;
+        }
+
+        let last_accepted_price = self.last_accepted_price(collateral_id).get();
+        let last_price = &last_accepted_price.price;
+        let price_diff = if price >= last_price {
+            price - last_price
+        } else {
+            last_price - price
+        };
+        let deviation_percentage = &(&price_diff * PERCENTAGE_PRECISION) / last_price;
+
+        let current_time = self.blockchain().get_block_timestamp();
+        let elapsed_periods =
+            (current_time - last_accepted_price.timestamp) / DEVIATION_SCALING_PERIOD_SECONDS;
+        let allowed_deviation_percentage = max_price_deviation_percentage * (1 + elapsed_periods);
+
+        deviation_percentage > allowed_deviation_percentage
+    }
+
+    // refreshed on every accepted price, so the deviation is always bounded relative to the latest one
+    fn update_last_accepted_price(&self, collateral_id: &TokenIdentifier, price: &BigUint) {
+        self.last_accepted_price(collateral_id).set(&AcceptedPrice {
+            price: price.clone(),
+            timestamp: self.blockchain().get_block_timestamp(),
+        });
+    }
+
+    // storage
+
+    #[view(getLastAcceptedPrice)]
+    #[storage_mapper("lastAcceptedPrice")]
+    fn last_accepted_price(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<AcceptedPrice<Self::Api>>;
+
+    #[view(getMaxPriceAgeSeconds)]
+    #[storage_mapper("maxPriceAgeSeconds")]
+    fn max_price_age_seconds(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<u64>;
+
+    #[view(getMaxPriceDeviationPercentage)]
+    #[storage_mapper("maxPriceDeviationPercentage")]
+    fn max_price_deviation_percentage(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<BigUint>;
+}
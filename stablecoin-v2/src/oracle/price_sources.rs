@@ -0,0 +1,288 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::{math::PRICE_NUM_DECIMALS, price_checks::PriceRound};
+use price_aggregator_proxy::DOLLAR_TICKER;
+
+mod aggregator_proxy {
+    elrond_wasm::imports!();
+
+    #[elrond_wasm::proxy]
+    pub trait PriceAggregator {
+        #[view(latestPriceFeedOptional)]
+        fn latest_price_feed_optional(
+            &self,
+            from: ManagedBuffer,
+            to: ManagedBuffer,
+        ) -> OptionalResult<MultiResult5<u32, ManagedBuffer, ManagedBuffer, BigUint, u8>>;
+    }
+}
+
+mod dex_pair_proxy {
+    elrond_wasm::imports!();
+
+    #[elrond_wasm::proxy]
+    pub trait DexPair {
+        #[view(getEquivalent)]
+        fn get_equivalent(&self, token_in: TokenIdentifier, amount_in: BigUint) -> BigUint;
+    }
+}
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum PriceSourceType {
+    PriceAggregator,
+    DexPair,
+}
+
+impl PriceSourceType {
+    pub fn from_index(index: u64) -> Option<Self> {
+        match index {
+            0 => Some(PriceSourceType::PriceAggregator),
+            1 => Some(PriceSourceType::DexPair),
+            _ => None,
+        }
+    }
+}
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone)]
+pub struct PriceSource<M: ManagedTypeApi> {
+    pub source_type: PriceSourceType,
+    pub address: ManagedAddress<M>,
+}
+
+// Collaterals without registered sources keep using the price aggregator set in init.
+// Sources are added and removed through governance proposals.
+// DEX pairs must be quoted against a dollar-pegged token, and are read as the equivalent of one whole collateral unit.
+// All quotes are scaled to the price decimals before the median is taken.
+// Aggregators are fresh as long as a new round was seen within the collateral's max price age.
+// DEX quotes come from the current reserves, so they are never stale, but they can be moved within a single transaction.
+// DEX pair sources must be under half of the quorum, so the median is always within the range of the other sources' prices
+#[elrond_wasm::module]
+pub trait PriceSourcesModule:
+    crate::math::MathModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+{
+    #[view(getPriceSources)]
+    fn get_price_sources(
+        &self,
+        collateral_id: TokenIdentifier,
+    ) -> MultiResultVec<PriceSource<Self::Api>> {
+        self.price_sources(&collateral_id).iter().collect()
+    }
+
+    // the quorum failing because of stale rounds gets its own error, so it can be told apart from unresponsive sources
+    fn get_price_from_sources(
+        &self,
+        collateral_id: &TokenIdentifier,
+        collateral_ticker: &ManagedBuffer,
+        collateral_precision: &BigUint,
+    ) -> SCResult<BigUint> {
+        let mut prices = Vec::new();
+        let mut has_stale_quote = false;
+        for source in self.get_collateral_price_sources(collateral_id) {
+            let opt_price = match source.source_type {
+                PriceSourceType::PriceAggregator => match self.query_price_aggregator(
+                    &source.address,
+                    collateral_ticker.clone(),
+                    ManagedBuffer::from(DOLLAR_TICKER),
+                ) {
+                    Some((round_id, price, decimals)) => {
+                        if self.update_source_round_and_check_fresh(
+                            collateral_id,
+                            &source,
+                            round_id,
+                        ) {
+                            Some(self.scale_decimals(&price, decimals as u32, PRICE_NUM_DECIMALS))
+                        } else {
+                            has_stale_quote = true;
+                            None
+                        }
+                    }
+                    None => None,
+                },
+                PriceSourceType::DexPair => {
+                    let quote_num_decimals =
+                        self.price_source_num_decimals(collateral_id, &source).get();
+                    let equivalent =
+                        self.query_dex_pair(&source.address, collateral_id, collateral_precision);
+
+                    Some(self.scale_decimals(&equivalent, quote_num_decimals, PRICE_NUM_DECIMALS))
+                }
+            };
+
+            if let Some(price) = opt_price {
+                prices.push(price);
+            }
+        }
+
+        let quorum = core::cmp::max(self.price_sources_quorum(collateral_id).get(), 1);
+        if prices.len() < quorum {
+            require!(!has_stale_quote, "Oracle price is stale");
+            return sc_error!("Could not get collateral value in dollars");
+        }
+
+        Ok(self.median(prices))
+    }
+
+    fn get_collateral_price_sources(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> Vec<PriceSource<Self::Api>> {
+        let price_sources = self.price_sources(collateral_id);
+        if price_sources.is_empty() {
+            return Vec::from([PriceSource {
+                source_type: PriceSourceType::PriceAggregator,
+                address: self.price_aggregator_address().get(),
+            }]);
+        }
+
+        price_sources.iter().collect()
+    }
+
+    // returns the round id and the price, along with the price's decimals
+    fn query_price_aggregator(
+        &self,
+        address: &ManagedAddress,
+        from: ManagedBuffer,
+        to: ManagedBuffer,
+    ) -> Option<(u32, BigUint, u8)> {
+        let result: OptionalResult<MultiResult5<u32, ManagedBuffer, ManagedBuffer, BigUint, u8>> =
+            self.aggregator_proxy(address.clone())
+                .latest_price_feed_optional(from, to)
+                .execute_on_dest_context();
+
+        result.into_option().map(|price_feed| {
+            let (round_id, _, _, price, decimals) = price_feed.into_tuple();
+            (round_id, price, decimals)
+        })
+    }
+
+    fn query_dex_pair(
+        &self,
+        address: &ManagedAddress,
+        collateral_id: &TokenIdentifier,
+        collateral_precision: &BigUint,
+    ) -> BigUint {
+        self.dex_pair_proxy(address.clone())
+            .get_equivalent(collateral_id.clone(), collateral_precision.clone())
+            .execute_on_dest_context()
+    }
+
+    // quote_num_decimals is the decimals of the DEX pair's dollar-pegged token
+    fn add_price_source(
+        &self,
+        collateral_id: &TokenIdentifier,
+        source: PriceSource<Self::Api>,
+        quote_num_decimals: u32,
+    ) {
+        if source.source_type == PriceSourceType::DexPair {
+            self.price_source_num_decimals(collateral_id, &source)
+                .set(&quote_num_decimals);
+        }
+        self.price_sources(collateral_id).insert(source);
+    }
+
+    fn remove_price_source(
+        &self,
+        collateral_id: &TokenIdentifier,
+        source: &PriceSource<Self::Api>,
+    ) {
+        self.price_sources(collateral_id).remove(source);
+        self.price_source_last_round(collateral_id, source).clear();
+        self.price_source_num_decimals(collateral_id, source)
+            .clear();
+    }
+
+    // collaterals without sources use the aggregator set in init, which counts as one source
+    fn require_valid_price_sources_quorum(
+        &self,
+        quorum: usize,
+        number_of_sources: usize,
+    ) -> SCResult<()> {
+        require!(
+            quorum <= core::cmp::max(number_of_sources, 1),
+            "Quorum may not be over the number of price sources"
+        );
+        Ok(())
+    }
+
+    // every price counted towards the quorum may be a DEX quote, so it's the worst case that's checked
+    fn require_valid_dex_price_sources_quorum(
+        &self,
+        quorum: usize,
+        number_of_dex_sources: usize,
+    ) -> SCResult<()> {
+        require!(
+            number_of_dex_sources * 2 < quorum,
+            "DEX price sources must be under half of the quorum"
+        );
+        Ok(())
+    }
+
+    fn get_number_of_dex_price_sources(&self, collateral_id: &TokenIdentifier) -> usize {
+        self.price_sources(collateral_id)
+            .iter()
+            .filter(|source| source.source_type == PriceSourceType::DexPair)
+            .count()
+    }
+
+    fn update_source_round_and_check_fresh(
+        &self,
+        collateral_id: &TokenIdentifier,
+        source: &PriceSource<Self::Api>,
+        round_id: u32,
+    ) -> bool {
+        let mapper = self.price_source_last_round(collateral_id, source);
+        if mapper.is_empty() || mapper.get().round_id != round_id {
+            mapper.set(&PriceRound {
+                round_id,
+                first_seen_timestamp: self.blockchain().get_block_timestamp(),
+            });
+            return true;
+        }
+
+        !self.is_round_stale(collateral_id, &mapper.get())
+    }
+
+    fn median(&self, mut prices: Vec<BigUint>) -> BigUint {
+        prices.sort();
+
+        let middle = prices.len() / 2;
+        if prices.len() % 2 == 1 {
+            return prices[middle].clone();
+        }
+
+        (&prices[middle - 1] + &prices[middle]) / 2u64
+    }
+
+    #[proxy]
+    fn aggregator_proxy(&self, address: ManagedAddress) -> aggregator_proxy::Proxy<Self::Api>;
+
+    #[proxy]
+    fn dex_pair_proxy(&self, address: ManagedAddress) -> dex_pair_proxy::Proxy<Self::Api>;
+
+    // storage
+
+    #[storage_mapper("priceSources")]
+    fn price_sources(&self, collateral_id: &TokenIdentifier) -> SetMapper<PriceSource<Self::Api>>;
+
+    #[view(getPriceSourcesQuorum)]
+    #[storage_mapper("priceSourcesQuorum")]
+    fn price_sources_quorum(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<usize>;
+
+    #[storage_mapper("priceSourceNumDecimals")]
+    fn price_source_num_decimals(
+        &self,
+        collateral_id: &TokenIdentifier,
+        source: &PriceSource<Self::Api>,
+    ) -> SingleValueMapper<u32>;
+
+    #[view(getPriceSourceLastRound)]
+    #[storage_mapper("priceSourceLastRound")]
+    fn price_source_last_round(
+        &self,
+        collateral_id: &TokenIdentifier,
+        source: &PriceSource<Self::Api>,
+    ) -> SingleValueMapper<PriceRound>;
+}
@@ -0,0 +1,239 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+use crate::pause::PausableAction;
+
+const MAX_PRICE_OBSERVATIONS: usize = 100;
+const MIN_OBSERVATION_INTERVAL_SECONDS: u64 = 60;
+// right after an observation overwrites the oldest one, the others only go this far back
+const MAX_TWAP_WINDOW_SECONDS: u64 =
+    (MAX_PRICE_OBSERVATIONS as u64 - 1) * MIN_OBSERVATION_INTERVAL_SECONDS;
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum PricedOperation {
+    Mint,
+    Burn,
+    HedgingOpen,
+    HedgingClose,
+    Liquidation,
+}
+
+impl PricedOperation {
+    pub fn from_index(index: u64) -> Option<Self> {
+        match index {
+            0 => Some(PricedOperation::Mint),
+            1 => Some(PricedOperation::Burn),
+            2 => Some(PricedOperation::HedgingOpen),
+            3 => Some(PricedOperation::HedgingClose),
+            4 => Some(PricedOperation::Liquidation),
+            _ => None,
+        }
+    }
+
+    pub fn all() -> [Self; 5] {
+        [
+            PricedOperation::Mint,
+            PricedOperation::Burn,
+            PricedOperation::HedgingOpen,
+            PricedOperation::HedgingClose,
+            PricedOperation::Liquidation,
+        ]
+    }
+}
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode)]
+pub struct PriceObservation<M: ManagedTypeApi> {
+    pub timestamp: u64,
+    pub price_cumulative: BigUint<M>,
+}
+
+#[elrond_wasm::module]
+pub trait TwapModule:
+    crate::math::MathModule
+    + crate::pause::PauseModule
+    + crate::pools::PoolsModule
+    + price_aggregator_proxy::PriceAggregatorModule
+    + crate::price_checks::PriceChecksModule
+    + crate::price_sources::PriceSourcesModule
+{
+    #[endpoint(updateTwap)]
+    fn update_twap(&self, collateral_id: TokenIdentifier) -> SCResult<()> {
+        self.require_collateral_in_whitelist(&collateral_id)?;
+        self.require_not_paused(&collateral_id, PausableAction::KeeperActions)?;
+
+        self.get_spot_price_and_update_twap(&collateral_id)?;
+
+        Ok(())
+    }
+
+    #[view(getTwap)]
+    fn get_twap(&self, collateral_id: TokenIdentifier, window_seconds: u64) -> SCResult<BigUint> {
+        self.calculate_twap(&collateral_id, window_seconds)
+    }
+
+    /// Reads the spot price, which also updates the accumulator,
+    /// and returns either the spot price or the TWAP, depending on the collateral's setting
+    fn get_collateral_price(
+        &self,
+        collateral_id: &TokenIdentifier,
+        operation: PricedOperation,
+    ) -> SCResult<BigUint> {
+        let spot_price = self.get_spot_price_and_update_twap(collateral_id)?;
+        if !self.use_twap_price(collateral_id, operation).get() {
+            return Ok(spot_price);
+        }
+
+        let twap_window_seconds = self.twap_window_seconds(collateral_id).get();
+        self.calculate_twap(collateral_id, twap_window_seconds)
+    }
+
+    fn get_spot_price_and_update_twap(&self, collateral_id: &TokenIdentifier) -> SCResult<BigUint> {
+        let spot_price = self.get_collateral_value_in_dollars(collateral_id)?;
+        self.update_price_accumulator(collateral_id, &spot_price);
+
+        Ok(spot_price)
+    }
+
+    fn update_price_accumulator(&self, collateral_id: &TokenIdentifier, spot_price: &BigUint) {
+        let current_time = self.blockchain().get_block_timestamp();
+        let accumulator_mapper = self.price_accumulator(collateral_id);
+
+        let accumulator = if accumulator_mapper.is_empty() {
+            PriceObservation {
+                timestamp: current_time,
+                price_cumulative: BigUint::zero(),
+            }
+        } else {
+            let mut accumulator = accumulator_mapper.get();
+            let time_diff = current_time - accumulator.timestamp;
+            if time_diff > 0 {
+                // the previous price was in effect for the whole interval
+                let last_price = self.twap_last_price(collateral_id).get();
+                accumulator.price_cumulative += &last_price * time_diff;
+                accumulator.timestamp = current_time;
+            }
+
+            accumulator
+        };
+
+        self.record_observation(collateral_id, &accumulator);
+        accumulator_mapper.set(&accumulator);
+        self.twap_last_price(collateral_id).set(spot_price);
+    }
+
+    fn record_observation(
+        &self,
+        collateral_id: &TokenIdentifier,
+        accumulator: &PriceObservation<Self::Api>,
+    ) {
+        let mut observations = self.price_observations(collateral_id);
+        let last_observation_timestamp = self.last_observation_timestamp(collateral_id).get();
+        if !observations.is_empty()
+            && accumulator.timestamp - last_observation_timestamp < MIN_OBSERVATION_INTERVAL_SECONDS
+        {
+            return;
+        }
+
+        // observations are kept in a ring buffer, overwriting the oldest one when full
+        if observations.len() < MAX_PRICE_OBSERVATIONS {
+            observations.push(accumulator);
+        } else {
+            let oldest_index_mapper = self.oldest_observation_index(collateral_id);
+            let oldest_index = core::cmp::max(oldest_index_mapper.get(), 1);
+            observations.set(oldest_index, accumulator);
+            oldest_index_mapper.set(&(oldest_index % MAX_PRICE_OBSERVATIONS + 1));
+        }
+
+        self.last_observation_timestamp(collateral_id)
+            .set(&accumulator.timestamp);
+    }
+
+    // longer windows would be over the price history kept, so the TWAP could never be computed
+    fn require_valid_twap_window(&self, window_seconds: &BigUint) -> SCResult<()> {
+        require!(
+            *window_seconds > 0u64 && *window_seconds <= MAX_TWAP_WINDOW_SECONDS,
+            "Invalid TWAP window"
+        );
+        Ok(())
+    }
+
+    /// The average is computed from the newest observation that is at least window_seconds old,
+    /// so the actual averaging period may be slightly longer than the requested window
+    fn calculate_twap(
+        &self,
+        collateral_id: &TokenIdentifier,
+        window_seconds: u64,
+    ) -> SCResult<BigUint> {
+        require!(window_seconds > 0, "Invalid TWAP window");
+        require!(
+            !self.price_accumulator(collateral_id).is_empty(),
+            "No price history for collateral"
+        );
+
+        let current_time = self.blockchain().get_block_timestamp();
+        let accumulator = self.price_accumulator(collateral_id).get();
+        let last_price = self.twap_last_price(collateral_id).get();
+        let current_price_cumulative =
+            accumulator.price_cumulative + &last_price * (current_time - accumulator.timestamp);
+
+        let mut opt_start_observation: Option<PriceObservation<Self::Api>> = None;
+        for observation in self.price_observations(collateral_id).iter() {
+            if current_time - observation.timestamp < window_seconds {
+                continue;
+            }
+
+            let is_newer = match &opt_start_observation {
+                Some(start_observation) => observation.timestamp > start_observation.timestamp,
+                None => true,
+            };
+            if is_newer {
+                opt_start_observation = Some(observation);
+            }
+        }
+
+        let start_observation = match opt_start_observation {
+            Some(observation) => observation,
+            None => return sc_error!("Not enough price history for the TWAP window"),
+        };
+
+        let time_diff = current_time - start_observation.timestamp;
+        Ok((current_price_cumulative - start_observation.price_cumulative) / time_diff)
+    }
+
+    // storage
+
+    #[storage_mapper("twapLastPrice")]
+    fn twap_last_price(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<BigUint>;
+
+    #[storage_mapper("priceAccumulator")]
+    fn price_accumulator(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> SingleValueMapper<PriceObservation<Self::Api>>;
+
+    #[storage_mapper("priceObservations")]
+    fn price_observations(
+        &self,
+        collateral_id: &TokenIdentifier,
+    ) -> VecMapper<PriceObservation<Self::Api>>;
+
+    #[storage_mapper("oldestObservationIndex")]
+    fn oldest_observation_index(&self, collateral_id: &TokenIdentifier)
+        -> SingleValueMapper<usize>;
+
+    #[storage_mapper("lastObservationTimestamp")]
+    fn last_observation_timestamp(&self, collateral_id: &TokenIdentifier)
+        -> SingleValueMapper<u64>;
+
+    #[view(getTwapWindowSeconds)]
+    #[storage_mapper("twapWindowSeconds")]
+    fn twap_window_seconds(&self, collateral_id: &TokenIdentifier) -> SingleValueMapper<u64>;
+
+    #[view(isTwapPriceUsed)]
+    #[storage_mapper("useTwapPrice")]
+    fn use_twap_price(
+        &self,
+        collateral_id: &TokenIdentifier,
+        operation: PricedOperation,
+    ) -> SingleValueMapper<bool>;
+}
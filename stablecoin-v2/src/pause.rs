@@ -0,0 +1,90 @@
+elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
+
+#[derive(TypeAbi, TopEncode, TopDecode, NestedEncode, NestedDecode, PartialEq, Clone, Copy)]
+pub enum PausableAction {
+    Mint,
+    Burn,
+    HedgingOpen,
+    LiquidityDeposit,
+    KeeperActions,
+}
+
+// Exits (closing positions, removing margin and removing liquidity) can never be paused,
+// they are only disabled once global settlement starts, as they are replaced by the settlement redeems
+#[elrond_wasm::module]
+pub trait PauseModule {
+    #[only_owner]
+    #[endpoint(pause)]
+    fn pause(
+        &self,
+        action: PausableAction,
+        #[var_args] opt_collateral_id: OptionalArg<TokenIdentifier>,
+    ) {
+        self.set_paused(action, opt_collateral_id.into_option(), true);
+    }
+
+    #[only_owner]
+    #[endpoint(unpause)]
+    fn unpause(
+        &self,
+        action: PausableAction,
+        #[var_args] opt_collateral_id: OptionalArg<TokenIdentifier>,
+    ) {
+        self.set_paused(action, opt_collateral_id.into_option(), false);
+    }
+
+    #[view(isPaused)]
+    fn is_paused(&self, collateral_id: &TokenIdentifier, action: PausableAction) -> bool {
+        self.global_settlement_active().get()
+            || self.globally_paused(action).get()
+            || self.collateral_paused(collateral_id, action).get()
+    }
+
+    fn set_paused(
+        &self,
+        action: PausableAction,
+        opt_collateral_id: Option<TokenIdentifier>,
+        paused: bool,
+    ) {
+        match opt_collateral_id {
+            Some(collateral_id) => self.collateral_paused(&collateral_id, action).set(&paused),
+            None => self.globally_paused(action).set(&paused),
+        }
+    }
+
+    fn require_not_paused(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: PausableAction,
+    ) -> SCResult<()> {
+        require!(!self.is_paused(collateral_id, action), "Action is paused");
+        Ok(())
+    }
+
+    fn require_no_global_settlement(&self) -> SCResult<()> {
+        require!(
+            !self.global_settlement_active().get(),
+            "Global settlement is active"
+        );
+        Ok(())
+    }
+
+    // storage
+
+    #[view(isGlobalSettlementActive)]
+    #[storage_mapper("globalSettlementActive")]
+    fn global_settlement_active(&self) -> SingleValueMapper<bool>;
+
+    #[view(isGloballyPaused)]
+    #[storage_mapper("globallyPaused")]
+    fn globally_paused(&self, action: PausableAction) -> SingleValueMapper<bool>;
+
+    #[view(isCollateralPaused)]
+    #[storage_mapper("collateralPaused")]
+    fn collateral_paused(
+        &self,
+        collateral_id: &TokenIdentifier,
+        action: PausableAction,
+    ) -> SingleValueMapper<bool>;
+}
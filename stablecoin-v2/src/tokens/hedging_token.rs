@@ -1,9 +1,19 @@
 elrond_wasm::imports!();
+elrond_wasm::derive_imports!();
 
 const HEDGING_TOKEN_NAME: &[u8] = b"HedgingToken";
 const HEDGING_TOKEN_TICKER: &[u8] = b"HEDGE";
 pub const NFT_AMOUNT: u32 = 1;
 
+#[derive(TypeAbi, TopEncode, TopDecode)]
+pub struct HedgingTokenAttributes<M: ManagedTypeApi> {
+    pub collateral_id: TokenIdentifier<M>,
+    pub deposit_amount: BigUint<M>,
+    pub covered_amount: BigUint<M>,
+    pub oracle_value_at_deposit_time: BigUint<M>,
+    pub creation_timestamp: u64,
+}
+
 #[elrond_wasm::module]
 pub trait HedgingTokenModule: crate::token_common::TokenCommonModule {
     #[only_owner]
@@ -47,11 +57,12 @@ pub trait HedgingTokenModule: crate::token_common::TokenCommonModule {
         self.set_local_roles(&token_id, &roles)
     }
 
-    fn create_hedging_token(&self) -> u64 {
+    fn create_hedging_token(&self, attributes: &HedgingTokenAttributes<Self::Api>) -> u64 {
         let token_id = self.hedging_token_id().get();
         let amount = BigUint::from(NFT_AMOUNT);
+        let name = ManagedBuffer::from(HEDGING_TOKEN_NAME);
 
-        self.create_nft(&token_id, &amount)
+        self.create_nft(&token_id, &amount, &name, attributes)
     }
 
     fn send_hedging_token(&self, to: &ManagedAddress, nft_nonce: u64) {
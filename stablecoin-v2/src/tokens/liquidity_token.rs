@@ -66,7 +66,8 @@ pub trait LiquidityTokenModule:
         // must keep at least 1 in SC's balance for NFTAddQuantity
         // ESDT metadata is deleted if the balance is 0
         let amount_plus_leftover = amount + 1u32;
-        let new_sft_nonce = self.create_nft(&token_id, &amount_plus_leftover);
+        let new_sft_nonce =
+            self.create_nft(&token_id, &amount_plus_leftover, &ManagedBuffer::new(), &());
         self.liq_sft_nonce_for_collateral(collateral_id)
             .set(&new_sft_nonce);
         self.collateral_for_liq_sft_nonce(new_sft_nonce)
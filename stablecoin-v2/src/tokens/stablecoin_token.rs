@@ -1,9 +1,9 @@
 elrond_wasm::imports!();
 
+use crate::math::STABLECOIN_NUM_DECIMALS;
+
 const STABLE_COIN_NAME: &[u8] = b"StableCoin";
 const STABLE_COIN_TICKER: &[u8] = b"STCOIN";
-const STABLE_COIN_NUM_DECIMALS: usize = 6;
-// pub const STABLE_COIN_PRECISION: u64 = 1_000_000;
 
 #[elrond_wasm::module]
 pub trait StablecoinTokenModule: crate::token_common::TokenCommonModule {
@@ -31,7 +31,7 @@ pub trait StablecoinTokenModule: crate::token_common::TokenCommonModule {
                 FungibleTokenProperties {
                     can_burn: true,
                     can_mint: true,
-                    num_decimals: STABLE_COIN_NUM_DECIMALS,
+                    num_decimals: STABLECOIN_NUM_DECIMALS as usize,
                     can_freeze: true,
                     can_wipe: true,
                     can_pause: true,
@@ -11,17 +11,23 @@ pub trait TokenCommonModule {
             .async_call()
     }
 
-    fn create_nft(&self, token_id: &TokenIdentifier, amount: &BigUint) -> u64 {
+    fn create_nft<T: elrond_wasm::elrond_codec::TopEncode>(
+        &self,
+        token_id: &TokenIdentifier,
+        amount: &BigUint,
+        name: &ManagedBuffer,
+        attributes: &T,
+    ) -> u64 {
         let mut uris = ManagedVec::new();
         uris.push(ManagedBuffer::new());
 
         self.send().esdt_nft_create(
             token_id,
             amount,
-            &ManagedBuffer::new(),
+            name,
             &BigUint::zero(),
             &ManagedBuffer::new(),
-            &(),
+            attributes,
             &uris,
         )
     }
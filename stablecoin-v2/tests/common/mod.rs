@@ -0,0 +1,74 @@
+// shared by the unit test files, which don't all use every helper
+#![allow(dead_code)]
+
+use elrond_wasm::types::{BigUint, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::HedgingPosition;
+use stablecoin_v2::economics::math::MathModule;
+use stablecoin_v2::economics::pools::{Pool, PoolsModule};
+use stablecoin_v2::ContractObj;
+
+pub const COLLATERAL_ID: &[u8] = b"WEGLD-abcdef";
+// $100, with the stablecoin's 6 decimals
+pub const COLLATERAL_PRICE: u64 = 100_000_000;
+
+pub fn to_biguint(sc: &ContractObj<TxContext>, value: u64) -> BigUint<TxContext> {
+    &sc.create_precision_biguint(0) * value
+}
+
+// the collaterals have no decimals in the tests, so the value is the amount times the price
+pub fn covered_value_in_stablecoin(
+    sc: &ContractObj<TxContext>,
+    covered_amount: u64,
+) -> BigUint<TxContext> {
+    to_biguint(sc, covered_amount * COLLATERAL_PRICE)
+}
+
+// everything covered at COLLATERAL_PRICE
+pub fn set_pool(
+    sc: &ContractObj<TxContext>,
+    collateral_amount: u64,
+    stablecoin_amount: u64,
+    collateral_reserves: u64,
+    total_collateral_covered: u64,
+) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.pool_for_collateral(&collateral_id).set(&Pool {
+        collateral_amount: to_biguint(sc, collateral_amount),
+        stablecoin_amount: to_biguint(sc, stablecoin_amount),
+        collateral_reserves: to_biguint(sc, collateral_reserves),
+        total_collateral_covered: to_biguint(sc, total_collateral_covered),
+        total_covered_value_in_stablecoin: covered_value_in_stablecoin(
+            sc,
+            total_collateral_covered,
+        ),
+    });
+}
+
+pub fn set_total_collateral_covered(sc: &ContractObj<TxContext>, amount: u64) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.update_pool(&collateral_id, |pool| {
+        pool.total_collateral_covered = to_biguint(sc, amount);
+        pool.total_covered_value_in_stablecoin = covered_value_in_stablecoin(sc, amount);
+    });
+}
+
+// opened at COLLATERAL_PRICE at the dummy block timestamp, without close orders
+pub fn create_position(
+    sc: &ContractObj<TxContext>,
+    deposit_amount: u64,
+    covered_amount: u64,
+) -> HedgingPosition<TxContext> {
+    HedgingPosition {
+        collateral_id: TokenIdentifier::from(COLLATERAL_ID),
+        deposit_amount: to_biguint(sc, deposit_amount),
+        covered_amount: to_biguint(sc, covered_amount),
+        oracle_value_at_deposit_time: to_biguint(sc, COLLATERAL_PRICE),
+        covered_value_in_stablecoin: covered_value_in_stablecoin(sc, covered_amount),
+        creation_timestamp: 0,
+        withdraw_amount_after_force_close: None,
+        stop_loss_price: None,
+        take_profit_price: None,
+        funding_index: to_biguint(sc, 0),
+    }
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::to_biguint;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::economics::math::MathModule;
+
+// $2,500.5, with the stablecoin's 6 decimals
+const COLLATERAL_PRICE: u64 = 2_500_500_000;
+
+fn check_conversions(collateral_num_decimals: u32) {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+    let one_token = sc.create_precision_biguint(collateral_num_decimals);
+
+    // 3 tokens are worth $7,501.5
+    let three_tokens = &one_token * 3u64;
+    let stablecoin_amount =
+        sc.convert_collateral_to_stablecoin(&three_tokens, &price, collateral_num_decimals);
+    assert!(stablecoin_amount == to_biguint(&sc, 7_501_500_000));
+
+    let collateral_amount =
+        sc.convert_stablecoin_to_collateral(&stablecoin_amount, &price, collateral_num_decimals);
+    assert!(collateral_amount == three_tokens);
+
+    // half a token is worth $1,250.25
+    let half_token = one_token / 2u64;
+    let stablecoin_amount =
+        sc.convert_collateral_to_stablecoin(&half_token, &price, collateral_num_decimals);
+    assert!(stablecoin_amount == to_biguint(&sc, 1_250_250_000));
+
+    let collateral_amount =
+        sc.convert_stablecoin_to_collateral(&stablecoin_amount, &price, collateral_num_decimals);
+    assert!(collateral_amount == half_token);
+}
+
+#[test]
+fn conversion_6_decimals_test() {
+    check_conversions(6);
+}
+
+#[test]
+fn conversion_8_decimals_test() {
+    check_conversions(8);
+}
+
+#[test]
+fn conversion_18_decimals_test() {
+    check_conversions(18);
+}
+
+// $0.000001 at $3 per token is less than the smallest unit of a 6-decimals token
+#[test]
+fn conversion_rounds_down_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let price = to_biguint(&sc, 3_000_000);
+    let one_stablecoin_unit = to_biguint(&sc, 1);
+
+    let amount_6_decimals = sc.convert_stablecoin_to_collateral(&one_stablecoin_unit, &price, 6);
+    assert!(amount_6_decimals == 0u64);
+
+    let amount_8_decimals = sc.convert_stablecoin_to_collateral(&one_stablecoin_unit, &price, 8);
+    assert!(amount_8_decimals == 33u64);
+
+    let amount_18_decimals = sc.convert_stablecoin_to_collateral(&one_stablecoin_unit, &price, 18);
+    assert!(amount_18_decimals == 333_333_333_333u64);
+}
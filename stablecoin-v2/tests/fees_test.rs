@@ -0,0 +1,189 @@
+mod common;
+
+use common::{set_pool, set_total_collateral_covered, to_biguint, COLLATERAL_ID};
+use elrond_wasm::types::{BigUint, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::liquidity_providers::LiquidityProvidersModule;
+use stablecoin_v2::economics::fees::{FeeBreakpoint, FeeCurveType, FeesModule};
+use stablecoin_v2::economics::math::{ONE, PERCENTAGE_PRECISION};
+use stablecoin_v2::economics::pools::PoolsModule;
+use stablecoin_v2::ContractObj;
+
+fn set_mint_fee_curve(sc: &ContractObj<TxContext>, start_fee: u64, end_fee: u64) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let curve = sc.create_linear_fee_curve(&to_biguint(sc, start_fee), &to_biguint(sc, end_fee));
+    sc.fee_curve(&collateral_id, FeeCurveType::Mint).set(&curve);
+}
+
+// a target of 500 out of 1,000
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+
+    sc.target_hedging_ratio()
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 2));
+    set_pool(&sc, 1_000, 0, 0, 0);
+
+    sc
+}
+
+#[test]
+fn refresh_fee_configuration_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    set_mint_fee_curve(&sc, 2_000, 1_000);
+
+    // the first operation creates the snapshot
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.get_current_hedging_ratio(&collateral_id) == 0u64);
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 2_000u64);
+    assert_eq!(sc.get_fee_configuration_timestamp(&collateral_id), 0);
+
+    // the snapshot is kept as long as the hedging ratio doesn't move
+    set_mint_fee_curve(&sc, 4_000, 2_000);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 2_000u64);
+
+    // halfway to the target
+    set_total_collateral_covered(&sc, 250);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.get_current_hedging_ratio(&collateral_id) == ONE / 2);
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 3_000u64);
+}
+
+#[test]
+fn hedging_ratio_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // only the covered collateral amount counts, not its value in stablecoin
+    set_total_collateral_covered(&sc, 500);
+    assert!(sc.calculate_current_hedging_ratio(&collateral_id) == ONE);
+
+    // no collateral, no target
+    sc.update_pool(&collateral_id, |pool| {
+        pool.collateral_amount = to_biguint(&sc, 0)
+    });
+    assert!(sc.calculate_current_hedging_ratio(&collateral_id) == 0u64);
+}
+
+// (hedging ratio, fee) pairs, flattened
+fn create_fee_curve(sc: &ContractObj<TxContext>, values: &[u64]) -> Vec<FeeBreakpoint<TxContext>> {
+    let values: Vec<BigUint<TxContext>> =
+        values.iter().map(|value| to_biguint(sc, *value)).collect();
+    sc.fee_curve_from_values(&values)
+}
+
+#[test]
+fn curve_fee_percentage_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // flat up to half the target, steep up to the target, then flat again
+    let curve = create_fee_curve(&sc, &[ONE / 2, 1_000, ONE, 5_000]);
+    sc.fee_curve(&collateral_id, FeeCurveType::Burn).set(&curve);
+
+    let fee_at = |hedging_ratio: u64| {
+        sc.calculate_curve_fee_percentage(
+            &collateral_id,
+            FeeCurveType::Burn,
+            &to_biguint(&sc, hedging_ratio),
+        )
+    };
+    assert!(fee_at(0) == 1_000u64);
+    assert!(fee_at(ONE / 2) == 1_000u64);
+    assert!(fee_at(ONE * 3 / 4) == 3_000u64);
+    assert!(fee_at(ONE) == 5_000u64);
+    assert!(fee_at(2 * ONE) == 5_000u64);
+
+    // no curve means no fees
+    assert!(
+        sc.calculate_curve_fee_percentage(
+            &collateral_id,
+            FeeCurveType::Mint,
+            &to_biguint(&sc, ONE)
+        ) == 0u64
+    );
+}
+
+#[test]
+fn slippage_over_target_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let curve = sc.create_linear_fee_curve(&to_biguint(&sc, 1_000), &to_biguint(&sc, 5_000));
+    sc.fee_curve(&collateral_id, FeeCurveType::Slippage)
+        .set(&curve);
+
+    set_total_collateral_covered(&sc, 250);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.calculate_slippage_percentage(&collateral_id) == 3_000u64);
+
+    // no slippage once the target is reached, whatever the curve's last fee
+    set_total_collateral_covered(&sc, 500);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.calculate_slippage_percentage(&collateral_id) == 0u64);
+
+    set_total_collateral_covered(&sc, 600);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.calculate_slippage_percentage(&collateral_id) == 0u64);
+}
+
+#[test]
+fn migrate_legacy_fee_curves_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.min_max_fees_percentage(&collateral_id)
+        .set(&(to_biguint(&sc, 1_000), to_biguint(&sc, 3_000)));
+    sc.min_max_slippage_percentage(&collateral_id)
+        .set(&(to_biguint(&sc, 2_000), to_biguint(&sc, 4_000)));
+
+    // a configuration calculated without curves charges no fees
+    set_total_collateral_covered(&sc, 250);
+    sc.refresh_fee_configuration_if_stale(&collateral_id);
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 0u64);
+
+    // halfway to the target, like the pre-upgrade min/max interpolation
+    assert!(sc.migrate_legacy_fee_curves(&collateral_id).is_ok());
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 2_000u64);
+    assert!(sc.get_burn_transaction_fees_percentage(&collateral_id) == 2_000u64);
+    assert!(sc.calculate_slippage_percentage(&collateral_id) == 3_000u64);
+    assert!(sc.min_max_fees_percentage(&collateral_id).is_empty());
+    assert!(sc.min_max_slippage_percentage(&collateral_id).is_empty());
+
+    // migrating again keeps the curves
+    assert!(sc.migrate_legacy_fee_curves(&collateral_id).is_ok());
+    assert!(sc.get_mint_transaction_fees_percentage(&collateral_id) == 2_000u64);
+}
+
+#[test]
+fn validate_fee_curve_test() {
+    let sc = setup();
+
+    let increasing_curve = create_fee_curve(&sc, &[0, 1_000, ONE, 5_000]);
+    let decreasing_curve = create_fee_curve(&sc, &[0, 5_000, ONE, 1_000]);
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Burn, &increasing_curve)
+        .is_ok());
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Mint, &decreasing_curve)
+        .is_ok());
+
+    // the fees must move in the curve's direction
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Burn, &decreasing_curve)
+        .is_err());
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Mint, &increasing_curve)
+        .is_err());
+
+    let unordered_curve = create_fee_curve(&sc, &[ONE, 1_000, 0, 5_000]);
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Burn, &unordered_curve)
+        .is_err());
+
+    let invalid_fee_curve = create_fee_curve(&sc, &[0, PERCENTAGE_PRECISION]);
+    assert!(sc
+        .require_valid_fee_curve(FeeCurveType::Burn, &invalid_fee_curve)
+        .is_err());
+
+    assert!(sc.require_valid_fee_curve(FeeCurveType::Burn, &[]).is_err());
+}
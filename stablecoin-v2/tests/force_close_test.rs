@@ -0,0 +1,170 @@
+mod common;
+
+use common::{create_position, set_pool, to_biguint, COLLATERAL_ID, COLLATERAL_PRICE};
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::{ForceCloseOrdering, HedgingAgentsModule};
+use stablecoin_v2::actors::keepers::KeepersModule;
+use stablecoin_v2::economics::fees::{CurrentFeeConfiguration, FeesModule};
+use stablecoin_v2::economics::math::PERCENTAGE_PRECISION;
+use stablecoin_v2::economics::pools::PoolsModule;
+use stablecoin_v2::ContractObj;
+
+fn add_position(
+    sc: &ContractObj<TxContext>,
+    nft_nonce: u64,
+    deposit_amount: u64,
+    covered_amount: u64,
+) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.set_hedging_position(
+        nft_nonce,
+        &create_position(sc, deposit_amount, covered_amount),
+    );
+    sc.open_position_nonces(&collateral_id).insert(nft_nonce);
+}
+
+// 1,100 out of 1,000 collateral covered, with a limit of 90%
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.hedging_ratio_limit()
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION * 9 / 10));
+    sc.force_close_ordering(&collateral_id)
+        .set(&ForceCloseOrdering::OldestFirst);
+    sc.current_fee_configuration(&collateral_id)
+        .set(&CurrentFeeConfiguration {
+            hedging_ratio: to_biguint(&sc, 0),
+            mint_fee_percentage: to_biguint(&sc, 0),
+            burn_fee_percentage: to_biguint(&sc, 0),
+            hedging_open_fee_percentage: to_biguint(&sc, 0),
+            hedging_close_fee_percentage: to_biguint(&sc, 0),
+            timestamp: 0,
+        });
+    set_pool(&sc, 1_000, 0, 150, 1_100);
+
+    add_position(&sc, 1, 20, 100);
+    add_position(&sc, 2, 30, 150);
+    add_position(&sc, 3, 100, 850);
+
+    sc
+}
+
+// the first position is closed whole, and only 100 of the second one's 150 are closed
+#[test]
+fn force_close_stops_at_limit_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    let results = sc.force_close_until_limit(&collateral_id, vec![1, 2, 3], &price);
+    assert_eq!(results, vec![(1, true), (2, true), (3, false)]);
+
+    let pool = sc.get_pool(&collateral_id);
+    assert!(pool.total_collateral_covered == 900u64);
+    assert!(pool.total_covered_value_in_stablecoin == 90_000_000_000u64);
+
+    let first_position = sc.hedging_position(1).get();
+    assert!(first_position.withdraw_amount_after_force_close == Some(to_biguint(&sc, 20)));
+    assert!(sc.pending_force_close_withdrawals(&collateral_id).get() == 20u64);
+
+    // the force-closed deposit moved to the pending withdrawals
+    assert!(sc.total_hedging_deposits(&collateral_id).get() == 130u64);
+
+    // the closed part's withdraw amount goes back into the deposit
+    let second_position = sc.hedging_position(2).get();
+    assert!(!second_position.is_closed());
+    assert!(second_position.covered_amount == 50u64);
+    assert!(second_position.covered_value_in_stablecoin == 5_000_000_000u64);
+    assert!(second_position.deposit_amount == 30u64);
+
+    // the NFT isn't in the contract, so it can't be refreshed
+    assert!(!sc.hedging_token_attributes_stale(1).get());
+    assert!(sc.hedging_token_attributes_stale(2).get());
+
+    let third_position = sc.hedging_position(3).get();
+    assert!(third_position.covered_amount == 850u64);
+
+    let open_position_nonces = sc.open_position_nonces(&collateral_id);
+    assert!(!open_position_nonces.contains(&1));
+    assert!(open_position_nonces.contains(&2));
+    assert!(open_position_nonces.contains(&3));
+}
+
+#[test]
+fn force_close_under_limit_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    sc.force_close_until_limit(&collateral_id, vec![1, 2], &price);
+    let results = sc.force_close_until_limit(&collateral_id, vec![2, 3], &price);
+    assert_eq!(results, vec![(2, false), (3, false)]);
+}
+
+#[test]
+fn force_close_listed_positions_only_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    let results = sc.force_close_until_limit(&collateral_id, vec![1], &price);
+    assert_eq!(results, vec![(1, true)]);
+    assert!(sc.get_pool(&collateral_id).total_collateral_covered == 1_000u64);
+}
+
+#[test]
+fn force_close_highest_leverage_first_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    assert_eq!(sc.get_force_close_candidates(&collateral_id), vec![1, 2, 3]);
+
+    // ties are broken by nonce, before the order is reversed
+    sc.force_close_ordering(&collateral_id)
+        .set(&ForceCloseOrdering::HighestLeverageFirst);
+    assert_eq!(sc.get_force_close_candidates(&collateral_id), vec![3, 2, 1]);
+
+    let results = sc.force_close_until_limit(&collateral_id, vec![3, 2, 1], &price);
+    assert_eq!(results, vec![(3, true), (2, false), (1, false)]);
+    assert!(sc.hedging_position(3).get().covered_amount == 650u64);
+    assert!(sc.hedging_position(1).get().covered_amount == 100u64);
+}
+
+#[test]
+fn force_close_order_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    // only the next candidate in the ordering may be closed, and unknown nonces are skipped
+    let results = sc.force_close_until_limit(&collateral_id, vec![2], &price);
+    assert_eq!(results, vec![(2, false)]);
+    let results = sc.force_close_until_limit(&collateral_id, vec![4], &price);
+    assert_eq!(results, vec![(4, false)]);
+
+    sc.force_close_ordering(&collateral_id)
+        .set(&ForceCloseOrdering::HighestLeverageFirst);
+    let results = sc.force_close_until_limit(&collateral_id, vec![1], &price);
+    assert_eq!(results, vec![(1, false)]);
+
+    // nothing was closed
+    assert!(sc.get_pool(&collateral_id).total_collateral_covered == 1_100u64);
+}
+
+// skipped nonces don't stop the rest of the batch, and a duplicate is skipped after being closed
+#[test]
+fn force_close_skips_ineligible_positions_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, COLLATERAL_PRICE);
+
+    let results = sc.force_close_until_limit(&collateral_id, vec![2, 1, 1, 4, 2], &price);
+    assert_eq!(
+        results,
+        vec![(2, false), (1, true), (1, false), (4, false), (2, true)]
+    );
+    assert!(sc.get_pool(&collateral_id).total_collateral_covered == 900u64);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::{create_position, set_pool, set_total_collateral_covered, to_biguint, COLLATERAL_ID};
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::HedgingAgentsModule;
+use stablecoin_v2::economics::fees::FeesModule;
+use stablecoin_v2::economics::funding::FundingModule;
+use stablecoin_v2::economics::math::PERCENTAGE_PRECISION;
+use stablecoin_v2::pause::PauseModule;
+use stablecoin_v2::tokens::liquidity_token::LiquidityTokenModule;
+use stablecoin_v2::ContractObj;
+
+const MAX_FUNDING_RATE: u64 = 1_000;
+
+// a target of 500 out of 1,000, with a limit of 1.5 times the target
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.target_hedging_ratio()
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 2));
+    sc.hedging_ratio_limit()
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION * 3 / 4));
+    sc.max_funding_rate_per_second(&collateral_id)
+        .set(&to_biguint(&sc, MAX_FUNDING_RATE));
+    set_pool(&sc, 1_000, 0, 200, 0);
+
+    sc
+}
+
+#[test]
+fn funding_rate_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // nothing is paid up to the target
+    set_total_collateral_covered(&sc, 500);
+    assert!(sc.calculate_funding_rate_per_second(&collateral_id) == 0u64);
+
+    // halfway between the target and the limit
+    set_total_collateral_covered(&sc, 625);
+    assert!(sc.calculate_funding_rate_per_second(&collateral_id) == MAX_FUNDING_RATE / 2);
+
+    set_total_collateral_covered(&sc, 750);
+    assert!(sc.calculate_funding_rate_per_second(&collateral_id) == MAX_FUNDING_RATE);
+    set_total_collateral_covered(&sc, 1_000);
+    assert!(sc.calculate_funding_rate_per_second(&collateral_id) == MAX_FUNDING_RATE);
+
+    sc.global_settlement_active().set(&true);
+    assert!(sc.calculate_funding_rate_per_second(&collateral_id) == 0u64);
+}
+
+#[test]
+fn funding_amount_test() {
+    let sc = setup();
+    let deposit_amount = to_biguint(&sc, 100);
+
+    assert!(
+        sc.calculate_funding_amount(&deposit_amount, &to_biguint(&sc, PERCENTAGE_PRECISION / 10))
+            == 10u64
+    );
+
+    // capped at the deposit
+    assert!(
+        sc.calculate_funding_amount(&deposit_amount, &to_biguint(&sc, 2 * PERCENTAGE_PRECISION))
+            == 100u64
+    );
+}
+
+#[test]
+fn settle_funding_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.cumulative_funding_index(&collateral_id)
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 10));
+    sc.liq_sft_nonce_for_collateral(&collateral_id).set(&1);
+    sc.collateral_amount_for_liq_token(1)
+        .set(&to_biguint(&sc, 500));
+
+    let mut hedging_position = create_position(&sc, 100, 300);
+    assert!(sc.calculate_accrued_funding(&hedging_position) == 10u64);
+
+    sc.settle_funding(&mut hedging_position);
+    assert!(hedging_position.deposit_amount == 90u64);
+    assert!(hedging_position.funding_index == PERCENTAGE_PRECISION / 10);
+    assert!(sc.calculate_accrued_funding(&hedging_position) == 0u64);
+    assert!(sc.collateral_amount_for_liq_token(1).get() == 510u64);
+}
+
+#[test]
+fn settle_funding_without_liq_tokens_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.cumulative_funding_index(&collateral_id)
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 10));
+
+    // the funding is left in the reserves
+    let mut hedging_position = create_position(&sc, 100, 300);
+    sc.settle_funding(&mut hedging_position);
+    assert!(hedging_position.deposit_amount == 90u64);
+    assert!(sc.collateral_amount_for_liq_token(0).get() == 0u64);
+    assert!(sc.get_pool_reserves(&collateral_id) == 200u64);
+
+    // closed positions don't pay funding
+    hedging_position.withdraw_amount_after_force_close = Some(to_biguint(&sc, 90));
+    hedging_position.funding_index = to_biguint(&sc, 0);
+    assert!(sc.calculate_accrued_funding(&hedging_position) == 0u64);
+}
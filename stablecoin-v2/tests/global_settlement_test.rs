@@ -0,0 +1,175 @@
+mod common;
+
+use common::{to_biguint, COLLATERAL_ID};
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::{HedgingAgentsModule, HedgingPosition};
+use stablecoin_v2::economics::fees::FeesModule;
+use stablecoin_v2::economics::pools::{Pool, PoolsModule};
+use stablecoin_v2::global_settlement::GlobalSettlementModule;
+use stablecoin_v2::ContractObj;
+
+const COLLATERAL_PRICE: u64 = 2_000_000;
+
+// 600 collateral backing the stablecoins, 100 in reserves and 20 in fees,
+// one open position with a claim of 50 at the deposit price and 30 waiting to be withdrawn after a force close
+fn setup(stablecoin_amount: u64) -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.whitelisted_collaterals().insert(collateral_id.clone());
+    sc.collateral_num_decimals(&collateral_id).set(&0);
+    sc.accumulated_tx_fees(&collateral_id)
+        .set(&to_biguint(&sc, 20));
+    sc.pool_for_collateral(&collateral_id).set(&Pool {
+        collateral_amount: to_biguint(&sc, 600),
+        stablecoin_amount: to_biguint(&sc, stablecoin_amount),
+        collateral_reserves: to_biguint(&sc, 100),
+        total_collateral_covered: to_biguint(&sc, 100),
+        total_covered_value_in_stablecoin: to_biguint(&sc, 200_000_000),
+    });
+
+    sc.set_hedging_position(
+        1,
+        &HedgingPosition {
+            collateral_id: collateral_id.clone(),
+            deposit_amount: to_biguint(&sc, 50),
+            covered_amount: to_biguint(&sc, 100),
+            oracle_value_at_deposit_time: to_biguint(&sc, COLLATERAL_PRICE),
+            covered_value_in_stablecoin: to_biguint(&sc, 200_000_000),
+            creation_timestamp: 0,
+            withdraw_amount_after_force_close: None,
+            stop_loss_price: None,
+            take_profit_price: None,
+            funding_index: to_biguint(&sc, 0),
+        },
+    );
+    sc.open_position_nonces(&collateral_id).insert(1);
+    sc.pending_force_close_withdrawals(&collateral_id)
+        .set(&to_biguint(&sc, 30));
+
+    sc
+}
+
+#[test]
+fn freeze_collateral_test() {
+    let sc = setup(1_000_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.freeze_collateral(&collateral_id, to_biguint(&sc, COLLATERAL_PRICE));
+
+    // nothing is set aside for the hedgers until the open position is counted
+    assert!(!sc.is_settlement_hedgers_claim_final(&collateral_id));
+    assert_eq!(
+        sc.settlement_positions_left_to_count(&collateral_id).get(),
+        1
+    );
+    assert!(sc.settlement_hedgers_claim_total(&collateral_id).get() == 30u64);
+    assert!(sc.get_pool(&collateral_id).collateral_reserves == 220u64);
+
+    // unknown nonces are skipped
+    sc.count_settlement_hedging_positions(&collateral_id, vec![2]);
+    assert_eq!(
+        sc.settlement_positions_left_to_count(&collateral_id).get(),
+        1
+    );
+
+    sc.count_settlement_hedging_positions(&collateral_id, vec![1]);
+    assert!(sc.is_settlement_hedgers_claim_final(&collateral_id));
+
+    // $1,000 worth of stablecoins at $2 per collateral
+    assert!(sc.settlement_stablecoin_claim(&collateral_id).get() == 500u64);
+    assert!(sc.settlement_hedgers_claim(&collateral_id).get() == 80u64);
+    assert!(sc.settlement_hedgers_claim_total(&collateral_id).get() == 80u64);
+    assert!(sc.settlement_price(&collateral_id).get() == COLLATERAL_PRICE);
+    assert!(sc.accumulated_tx_fees(&collateral_id).get() == 0u64);
+
+    let pool = sc.get_pool(&collateral_id);
+    assert!(pool.collateral_amount == 0u64);
+    assert!(pool.stablecoin_amount == 0u64);
+    assert!(pool.collateral_reserves == 140u64);
+
+    // a position is only counted once
+    sc.count_settlement_hedging_positions(&collateral_id, vec![1]);
+    assert!(sc.settlement_hedgers_claim_total(&collateral_id).get() == 80u64);
+}
+
+// without open positions, the hedgers' claim is set aside as soon as the price is frozen
+#[test]
+fn freeze_collateral_without_open_positions_test() {
+    let sc = setup(1_000_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.open_position_nonces(&collateral_id).remove(&1);
+    sc.freeze_collateral(&collateral_id, to_biguint(&sc, COLLATERAL_PRICE));
+
+    assert!(sc.is_settlement_hedgers_claim_final(&collateral_id));
+    assert!(sc.settlement_hedgers_claim(&collateral_id).get() == 30u64);
+    assert!(sc.get_pool(&collateral_id).collateral_reserves == 190u64);
+}
+
+#[test]
+fn freeze_collateral_hedgers_pro_rata_test() {
+    let sc = setup(1_300_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.freeze_collateral(&collateral_id, to_biguint(&sc, COLLATERAL_PRICE));
+    sc.count_settlement_hedging_positions(&collateral_id, vec![1]);
+
+    // only 70 are left after the stablecoin holders' 650, out of the hedgers' 80
+    assert!(sc.settlement_stablecoin_claim(&collateral_id).get() == 650u64);
+    assert!(sc.settlement_hedgers_claim(&collateral_id).get() == 70u64);
+    assert!(sc.settlement_hedgers_claim_total(&collateral_id).get() == 80u64);
+    assert!(sc.get_pool(&collateral_id).collateral_reserves == 0u64);
+
+    // 50 * 70 / 80, rounded down
+    let hedging_position = sc.hedging_position(1).get();
+    assert!(sc.calculate_hedger_settlement_payout(hedging_position) == 43u64);
+}
+
+#[test]
+fn freeze_collateral_undercollateralized_test() {
+    let sc = setup(1_000_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // at $1, the stablecoins are worth 1,000 collateral, more than the 720 held
+    sc.freeze_collateral(&collateral_id, to_biguint(&sc, COLLATERAL_PRICE / 2));
+    sc.count_settlement_hedging_positions(&collateral_id, vec![1]);
+
+    assert!(sc.settlement_stablecoin_claim(&collateral_id).get() == 720u64);
+    assert!(sc.settlement_hedgers_claim(&collateral_id).get() == 0u64);
+    assert!(sc.settlement_hedgers_claim_total(&collateral_id).get() == 30u64);
+
+    // the position lost its whole deposit
+    let hedging_position = sc.hedging_position(1).get();
+    assert!(sc.calculate_hedger_settlement_payout(hedging_position) == 0u64);
+}
+
+#[test]
+fn stablecoin_settlement_claims_test() {
+    let sc = setup(1_000_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.settlement_stablecoin_supply()
+        .set(&to_biguint(&sc, 1_000_000_000));
+    sc.freeze_collateral(&collateral_id, to_biguint(&sc, COLLATERAL_PRICE));
+
+    let claims = sc.calculate_stablecoin_settlement_claims(&to_biguint(&sc, 100_000_000));
+    assert_eq!(claims.len(), 1);
+    assert!(claims[0].0 == collateral_id);
+    assert!(claims[0].1 == 50u64);
+}
+
+#[test]
+fn liquidity_settlement_claim_test() {
+    let sc = setup(1_000_000_000);
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.settlement_liq_providers_claim(&collateral_id)
+        .set(&to_biguint(&sc, 120));
+    sc.settlement_liq_tokens_supply(&collateral_id)
+        .set(&to_biguint(&sc, 1_000));
+
+    let claim = sc.calculate_liquidity_settlement_claim(&collateral_id, &to_biguint(&sc, 250));
+    assert!(claim == 30u64);
+}
@@ -0,0 +1,281 @@
+mod common;
+
+use common::{set_pool, to_biguint, COLLATERAL_ID, COLLATERAL_PRICE};
+use elrond_wasm::types::{Address, ManagedAddress, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::{HedgingAgentsModule, HedgingPosition};
+use stablecoin_v2::economics::fees::{CurrentFeeConfiguration, FeesModule};
+use stablecoin_v2::economics::math::PERCENTAGE_PRECISION;
+use stablecoin_v2::tokens::hedging_token::HedgingTokenModule;
+use stablecoin_v2::ContractObj;
+
+// 300 collateral covered at $100, with a deposit of 100 and a stop loss at $50
+fn create_position(sc: &ContractObj<TxContext>) -> HedgingPosition<TxContext> {
+    HedgingPosition {
+        stop_loss_price: Some(to_biguint(sc, COLLATERAL_PRICE / 2)),
+        ..common::create_position(sc, 100, 300)
+    }
+}
+
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.target_hedging_ratio()
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 2));
+    set_pool(&sc, 1_000, 100_000_000_000, 200, 300);
+    sc.current_fee_configuration(&collateral_id)
+        .set(&CurrentFeeConfiguration {
+            hedging_ratio: sc.calculate_current_hedging_ratio(&collateral_id),
+            mint_fee_percentage: to_biguint(&sc, 0),
+            burn_fee_percentage: to_biguint(&sc, 0),
+            hedging_open_fee_percentage: to_biguint(&sc, 0),
+            hedging_close_fee_percentage: to_biguint(&sc, 0),
+            timestamp: 0,
+        });
+    sc.set_hedging_position(1, &create_position(&sc));
+
+    sc
+}
+
+#[test]
+fn split_hedging_position_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let mut hedging_position = create_position(&sc);
+
+    let closed_part = sc.split_hedging_position(&mut hedging_position, to_biguint(&sc, 100));
+
+    // the deposit and the covered value are split proportionally, rounding down for the closed part
+    assert!(closed_part.deposit_amount == 33u64);
+    assert!(closed_part.covered_amount == 100u64);
+    assert!(closed_part.covered_value_in_stablecoin == 10_000_000_000u64);
+    assert!(closed_part.oracle_value_at_deposit_time == COLLATERAL_PRICE);
+    assert!(closed_part.stop_loss_price.is_none());
+
+    assert!(hedging_position.deposit_amount == 67u64);
+    assert!(hedging_position.covered_amount == 200u64);
+    assert!(hedging_position.covered_value_in_stablecoin == 20_000_000_000u64);
+    assert!(hedging_position.stop_loss_price == Some(to_biguint(&sc, COLLATERAL_PRICE / 2)));
+}
+
+#[test]
+fn partial_close_invalid_amount_test() {
+    let sc = setup();
+    let min_oracle_value = to_biguint(&sc, 0);
+
+    assert!(sc
+        .partial_close_position(1, to_biguint(&sc, 0), min_oracle_value.clone())
+        .is_err());
+    assert!(sc
+        .partial_close_position(1, to_biguint(&sc, 300), min_oracle_value.clone())
+        .is_err());
+    assert!(sc
+        .partial_close_position(2, to_biguint(&sc, 100), min_oracle_value)
+        .is_err());
+}
+
+#[test]
+fn partial_close_closed_position_test() {
+    let sc = setup();
+
+    let mut hedging_position = create_position(&sc);
+    hedging_position.withdraw_amount_after_force_close = Some(to_biguint(&sc, 100));
+    sc.set_hedging_position(1, &hedging_position);
+
+    assert!(sc
+        .partial_close_position(1, to_biguint(&sc, 100), to_biguint(&sc, 0))
+        .is_err());
+}
+
+#[test]
+fn increase_coverage_invalid_position_test() {
+    let sc = setup();
+    let max_oracle_value = to_biguint(&sc, COLLATERAL_PRICE);
+
+    assert!(sc
+        .increase_position_coverage(1, to_biguint(&sc, 0), max_oracle_value.clone())
+        .is_err());
+    assert!(sc
+        .increase_position_coverage(2, to_biguint(&sc, 100), max_oracle_value.clone())
+        .is_err());
+
+    let mut hedging_position = create_position(&sc);
+    hedging_position.withdraw_amount_after_force_close = Some(to_biguint(&sc, 100));
+    sc.set_hedging_position(1, &hedging_position);
+
+    assert!(sc
+        .increase_position_coverage(1, to_biguint(&sc, 100), max_oracle_value)
+        .is_err());
+}
+
+#[test]
+fn increase_coverage_fees_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.current_fee_configuration(&collateral_id)
+        .update(|fee_configuration| {
+            fee_configuration.hedging_open_fee_percentage =
+                to_biguint(&sc, PERCENTAGE_PRECISION / 10)
+        });
+
+    // doubling the coverage, half of the deposit of 100 backs the added 300
+    let hedging_position = create_position(&sc);
+    let fees_amount =
+        sc.calculate_coverage_increase_fees_amount(&hedging_position, &to_biguint(&sc, 300));
+    assert!(fees_amount == 5u64);
+}
+
+#[test]
+fn invalid_close_orders_test() {
+    let sc = setup();
+
+    assert!(sc
+        .set_position_close_orders(
+            1,
+            to_biguint(&sc, COLLATERAL_PRICE),
+            to_biguint(&sc, COLLATERAL_PRICE)
+        )
+        .is_err());
+    assert!(sc
+        .set_position_close_orders(
+            2,
+            to_biguint(&sc, COLLATERAL_PRICE / 2),
+            to_biguint(&sc, 2 * COLLATERAL_PRICE)
+        )
+        .is_err());
+
+    // the previous orders are kept
+    let hedging_position = sc.hedging_position(1).get();
+    assert!(hedging_position.stop_loss_price == Some(to_biguint(&sc, COLLATERAL_PRICE / 2)));
+    assert!(hedging_position.take_profit_price.is_none());
+
+    let mut closed_position = create_position(&sc);
+    closed_position.withdraw_amount_after_force_close = Some(to_biguint(&sc, 100));
+    sc.set_hedging_position(1, &closed_position);
+
+    assert!(sc
+        .set_position_close_orders(1, to_biguint(&sc, 0), to_biguint(&sc, 0))
+        .is_err());
+}
+
+#[test]
+fn hedging_token_attributes_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let hedging_position = create_position(&sc);
+
+    let attributes = sc.get_hedging_token_attributes(&hedging_position);
+    assert!(attributes.collateral_id == TokenIdentifier::from(COLLATERAL_ID));
+    assert!(attributes.deposit_amount == 100u64);
+    assert!(attributes.covered_amount == 300u64);
+    assert!(attributes.oracle_value_at_deposit_time == COLLATERAL_PRICE);
+    assert_eq!(attributes.creation_timestamp, 0);
+}
+
+#[test]
+fn position_health_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let hedging_position = create_position(&sc);
+
+    // deposit / covered = 1 / 3
+    assert!(
+        sc.calculate_margin_ratio(&hedging_position, &to_biguint(&sc, COLLATERAL_PRICE))
+            == 3_333_333u64
+    );
+    assert!(
+        sc.calculate_margin_ratio(&hedging_position, &to_biguint(&sc, 2 * COLLATERAL_PRICE))
+            == 8_333_333u64
+    );
+    assert!(
+        sc.calculate_margin_ratio(&hedging_position, &to_biguint(&sc, COLLATERAL_PRICE / 2))
+            == 0u64
+    );
+    assert!(
+        sc.calculate_leverage(
+            &hedging_position.deposit_amount,
+            &hedging_position.covered_amount
+        ) == 40_000_000u64
+    );
+
+    // $100 / (1 + 1 / 3 - 0.1)
+    sc.hedging_maintenance_ratio(&collateral_id)
+        .set(&to_biguint(&sc, 1_000_000));
+    assert!(sc.calculate_liquidation_price(&hedging_position) == Some(to_biguint(&sc, 81_081_083)));
+
+    // the margin at entry is already under the maintenance ratio
+    sc.hedging_maintenance_ratio(&collateral_id)
+        .set(&to_biguint(&sc, 14_000_000));
+    assert!(sc.calculate_liquidation_price(&hedging_position).is_none());
+}
+
+#[test]
+fn position_health_invalid_position_test() {
+    let sc = setup();
+
+    assert!(sc.get_hedging_position_health(2).is_err());
+
+    let mut closed_position = create_position(&sc);
+    closed_position.withdraw_amount_after_force_close = Some(to_biguint(&sc, 100));
+    sc.set_hedging_position(1, &closed_position);
+
+    assert!(sc.get_hedging_position_health(1).is_err());
+}
+
+#[test]
+fn deposited_hedging_token_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let hedging_token_id = TokenIdentifier::from(&b"HEDGE-abcdef"[..]);
+    let previous_owner = ManagedAddress::from_address(&Address::from([3u8; 32]));
+    sc.hedging_token_id().set(&hedging_token_id);
+    sc.add_to_position_indexes(1, &collateral_id, &previous_owner);
+
+    assert!(sc
+        .set_close_orders_deposited(1, to_biguint(&sc, 0), to_biguint(&sc, 0))
+        .is_err());
+    assert!(sc.withdraw_hedging_token(1).is_err());
+    assert!(sc
+        .deposit_hedging_token(TokenIdentifier::from(COLLATERAL_ID), 1)
+        .is_err());
+    assert!(sc
+        .deposit_hedging_token(hedging_token_id.clone(), 2)
+        .is_err());
+
+    // the position now belongs to whoever deposited the NFT
+    assert!(sc.deposit_hedging_token(hedging_token_id, 1).is_ok());
+    let depositor = sc.deposited_hedging_token_owner(1).get();
+    assert!(sc.position_owner(1).get() == depositor);
+    assert!(sc.owner_position_nonces(&depositor).contains(&1));
+    assert!(!sc.owner_position_nonces(&previous_owner).contains(&1));
+    assert!(sc.open_position_nonces(&collateral_id).contains(&1));
+
+    // the NFT stays in the contract
+    assert!(sc
+        .set_close_orders_deposited(1, to_biguint(&sc, 0), to_biguint(&sc, 2 * COLLATERAL_PRICE))
+        .is_ok());
+    let hedging_position = sc.hedging_position(1).get();
+    assert!(hedging_position.stop_loss_price.is_none());
+    assert!(hedging_position.take_profit_price == Some(to_biguint(&sc, 2 * COLLATERAL_PRICE)));
+}
+
+#[test]
+fn deposit_force_closed_hedging_token_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let hedging_token_id = TokenIdentifier::from(&b"HEDGE-abcdef"[..]);
+    let previous_owner = ManagedAddress::from_address(&Address::from([3u8; 32]));
+    sc.hedging_token_id().set(&hedging_token_id);
+    sc.add_to_position_indexes(1, &collateral_id, &previous_owner);
+
+    let mut hedging_position = create_position(&sc);
+    sc.set_position_force_closed(1, &mut hedging_position, to_biguint(&sc, 100));
+
+    // the position changes owner, but is not counted as open again
+    assert!(sc.deposit_hedging_token(hedging_token_id, 1).is_ok());
+    let depositor = sc.deposited_hedging_token_owner(1).get();
+    assert!(sc.position_owner(1).get() == depositor);
+    assert!(sc.owner_position_nonces(&depositor).contains(&1));
+    assert!(!sc.owner_position_nonces(&previous_owner).contains(&1));
+    assert!(!sc.open_position_nonces(&collateral_id).contains(&1));
+    assert!(sc.pending_force_close_withdrawals(&collateral_id).get() == 100u64);
+}
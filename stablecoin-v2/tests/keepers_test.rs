@@ -0,0 +1,196 @@
+mod common;
+
+use common::{create_position, set_pool, to_biguint, COLLATERAL_ID, COLLATERAL_PRICE};
+use elrond_wasm::types::{Address, ManagedAddress, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::HedgingAgentsModule;
+use stablecoin_v2::actors::keepers::{KeeperAction, KeepersModule};
+use stablecoin_v2::economics::fees::FeesModule;
+use stablecoin_v2::economics::math::PERCENTAGE_PRECISION;
+use stablecoin_v2::economics::pools::PoolsModule;
+use stablecoin_v2::ContractObj;
+
+fn set_reserves(sc: &ContractObj<TxContext>, reserves: u64) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.update_pool(&collateral_id, |pool| {
+        pool.collateral_reserves = to_biguint(sc, reserves)
+    });
+}
+
+// one position of 300 collateral covered at $100, with a deposit of 100,
+// a maintenance ratio of 10% and a liquidation bounty of 10%
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let owner = ManagedAddress::from_address(&Address::zero());
+
+    sc.hedging_maintenance_ratio(&collateral_id)
+        .set(&to_biguint(&sc, 1_000_000));
+    sc.liquidation_bounty_percentage(&collateral_id)
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 10));
+    set_pool(&sc, 1_000, 100_000_000_000, 200, 300);
+
+    sc.set_hedging_position(1, &create_position(&sc, 100, 300));
+    sc.add_to_position_indexes(1, &collateral_id, &owner);
+
+    sc
+}
+
+#[test]
+fn bounty_amount_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let bounty_percentage = to_biguint(&sc, PERCENTAGE_PRECISION / 10);
+
+    assert!(
+        sc.calculate_bounty_amount(&collateral_id, &bounty_percentage, &to_biguint(&sc, 25))
+            == 2u64
+    );
+    assert!(
+        sc.calculate_bounty_amount(&collateral_id, &bounty_percentage, &to_biguint(&sc, 1_000))
+            == 100u64
+    );
+
+    // capped at the reserves
+    set_reserves(&sc, 50);
+    assert!(
+        sc.calculate_bounty_amount(&collateral_id, &bounty_percentage, &to_biguint(&sc, 1_000))
+            == 50u64
+    );
+}
+
+#[test]
+fn liquidate_healthy_position_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // a margin ratio of 1 / 3 at the deposit price
+    assert!(sc
+        .liquidate_position(1, &to_biguint(&sc, COLLATERAL_PRICE))
+        .is_err());
+
+    assert!(!sc.hedging_position(1).is_empty());
+    assert!(sc.open_position_nonces(&collateral_id).contains(&1));
+    assert!(sc.get_pool(&collateral_id).total_collateral_covered == 300u64);
+}
+
+#[test]
+fn liquidate_position_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let owner = ManagedAddress::from_address(&Address::zero());
+
+    // at $80, the margin ratio is 1 / 3 - 1 / 4, under the maintenance ratio.
+    // There are no reserves left to pay the bounty from, so the remaining deposit all goes to the reserves
+    set_reserves(&sc, 0);
+    assert!(sc
+        .liquidate_position(1, &to_biguint(&sc, 80_000_000))
+        .is_ok());
+
+    assert!(sc.hedging_position(1).is_empty());
+    assert!(!sc.open_position_nonces(&collateral_id).contains(&1));
+    assert!(!sc.owner_position_nonces(&owner).contains(&1));
+    assert!(sc.position_owner(1).is_empty());
+
+    let pool = sc.get_pool(&collateral_id);
+    assert!(pool.collateral_reserves == 0u64);
+    assert!(pool.total_collateral_covered == 0u64);
+    assert!(pool.total_covered_value_in_stablecoin == 0u64);
+}
+
+#[test]
+fn batch_liquidate_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    let mut other_position = sc.hedging_position(1).get();
+    other_position.collateral_id = TokenIdentifier::from(&b"WBTC-abcdef"[..]);
+    sc.set_hedging_position(2, &other_position);
+
+    // positions of other collaterals and liquidated ones are skipped
+    set_reserves(&sc, 0);
+    let results = sc.liquidate_positions(
+        &collateral_id,
+        vec![2, 1, 1, 3],
+        &to_biguint(&sc, 80_000_000),
+    );
+    assert_eq!(results, vec![(2, false), (1, true), (1, false), (3, false)]);
+    assert!(!sc.hedging_position(2).is_empty());
+}
+
+#[test]
+fn expire_position_not_expired_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // positions don't expire without a max hedging period
+    assert!(sc.expire_hedging_position(1).is_err());
+
+    sc.max_hedging_period_seconds(&collateral_id).set(&60);
+    assert!(sc.expire_hedging_position(1).is_err());
+    assert!(sc.expire_hedging_position(2).is_err());
+
+    assert!(sc
+        .hedging_position(1)
+        .get()
+        .withdraw_amount_after_force_close
+        .is_none());
+    assert!(sc.open_position_nonces(&collateral_id).contains(&1));
+}
+
+#[test]
+fn keeper_reward_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.keeper_reward_amount(&collateral_id, KeeperAction::SplitFees)
+        .set(&to_biguint(&sc, 10));
+    sc.accumulated_tx_fees(&collateral_id)
+        .set(&to_biguint(&sc, 25));
+
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::SplitFees) == 10u64);
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::SplitFees) == 10u64);
+
+    // capped at the accumulated fees
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::SplitFees) == 5u64);
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::SplitFees) == 0u64);
+    assert!(sc.accumulated_tx_fees(&collateral_id).get() == 0u64);
+
+    // other actions have their own reward
+    sc.accumulated_tx_fees(&collateral_id)
+        .set(&to_biguint(&sc, 25));
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::RebalancePool) == 0u64);
+    assert!(sc.accumulated_tx_fees(&collateral_id).get() == 25u64);
+}
+
+#[test]
+fn keeper_reward_min_interval_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.keeper_reward_amount(&collateral_id, KeeperAction::SplitFees)
+        .set(&to_biguint(&sc, 10));
+    sc.keeper_reward_min_interval_seconds(&collateral_id, KeeperAction::SplitFees)
+        .set(&60);
+    sc.accumulated_tx_fees(&collateral_id)
+        .set(&to_biguint(&sc, 25));
+
+    assert!(sc.claim_keeper_reward(&collateral_id, KeeperAction::SplitFees) == 0u64);
+    assert!(sc.accumulated_tx_fees(&collateral_id).get() == 25u64);
+    assert_eq!(
+        sc.last_keeper_action_timestamp(&collateral_id, KeeperAction::SplitFees)
+            .get(),
+        0
+    );
+}
+
+#[test]
+fn update_fees_percentage_not_whitelisted_test() {
+    let sc = setup();
+    let other_collateral_id = TokenIdentifier::from(&b"WBTC-abcdef"[..]);
+
+    assert!(sc
+        .update_fees_percentage(other_collateral_id.clone())
+        .is_err());
+    assert!(sc
+        .current_fee_configuration(&other_collateral_id)
+        .is_empty());
+}
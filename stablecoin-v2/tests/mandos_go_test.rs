@@ -1,6 +1,44 @@
-/*
 #[test]
-fn unwrap_egld_go() {
-    elrond_wasm_debug::mandos_go("mandos/.scen.json");
+fn init_go() {
+    elrond_wasm_debug::mandos_go("mandos/init.scen.json");
+}
+
+#[test]
+fn whitelist_collateral_go() {
+    elrond_wasm_debug::mandos_go("mandos/whitelist-collateral.scen.json");
+}
+
+#[test]
+fn governance_go() {
+    elrond_wasm_debug::mandos_go("mandos/governance.scen.json");
+}
+
+#[test]
+fn pause_go() {
+    elrond_wasm_debug::mandos_go("mandos/pause.scen.json");
+}
+
+#[test]
+fn remove_collateral_go() {
+    elrond_wasm_debug::mandos_go("mandos/remove-collateral.scen.json");
+}
+
+#[test]
+fn setup_go() {
+    elrond_wasm_debug::mandos_go("mandos/setup.scen.json");
+}
+
+#[test]
+fn swap_go() {
+    elrond_wasm_debug::mandos_go("mandos/swap.scen.json");
+}
+
+#[test]
+fn liquidity_go() {
+    elrond_wasm_debug::mandos_go("mandos/liquidity.scen.json");
+}
+
+#[test]
+fn hedging_position_go() {
+    elrond_wasm_debug::mandos_go("mandos/hedging-position.scen.json");
 }
-*/
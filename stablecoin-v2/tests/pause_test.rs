@@ -0,0 +1,68 @@
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::pause::{PausableAction, PauseModule};
+
+const COLLATERAL_ID: &[u8] = b"WEGLD-abcdef";
+const OTHER_COLLATERAL_ID: &[u8] = b"WBTC-abcdef";
+
+#[test]
+fn collateral_pause_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let other_collateral_id = TokenIdentifier::from(OTHER_COLLATERAL_ID);
+
+    sc.set_paused(PausableAction::Mint, Some(collateral_id.clone()), true);
+    assert!(sc.is_paused(&collateral_id, PausableAction::Mint));
+    assert!(!sc.is_paused(&collateral_id, PausableAction::Burn));
+    assert!(!sc.is_paused(&other_collateral_id, PausableAction::Mint));
+    assert!(sc
+        .require_not_paused(&collateral_id, PausableAction::Mint)
+        .is_err());
+
+    sc.set_paused(PausableAction::Mint, Some(collateral_id.clone()), false);
+    assert!(!sc.is_paused(&collateral_id, PausableAction::Mint));
+}
+
+#[test]
+fn global_pause_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let other_collateral_id = TokenIdentifier::from(OTHER_COLLATERAL_ID);
+
+    sc.set_paused(PausableAction::HedgingOpen, None, true);
+    assert!(sc.is_paused(&collateral_id, PausableAction::HedgingOpen));
+    assert!(sc.is_paused(&other_collateral_id, PausableAction::HedgingOpen));
+
+    // unpausing a single collateral doesn't override the global switch
+    sc.set_paused(
+        PausableAction::HedgingOpen,
+        Some(collateral_id.clone()),
+        false,
+    );
+    assert!(sc.is_paused(&collateral_id, PausableAction::HedgingOpen));
+
+    sc.set_paused(PausableAction::HedgingOpen, None, false);
+    assert!(!sc.is_paused(&collateral_id, PausableAction::HedgingOpen));
+}
+
+// global settlement pauses every action, and nothing can unpause them
+#[test]
+fn global_settlement_pauses_everything_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.global_settlement_active().set(&true);
+    for action in [
+        PausableAction::Mint,
+        PausableAction::Burn,
+        PausableAction::HedgingOpen,
+        PausableAction::LiquidityDeposit,
+        PausableAction::KeeperActions,
+    ]
+    .iter()
+    {
+        sc.set_paused(*action, None, false);
+        assert!(sc.is_paused(&collateral_id, *action));
+    }
+    assert!(sc.require_no_global_settlement().is_err());
+}
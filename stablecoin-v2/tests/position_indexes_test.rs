@@ -0,0 +1,59 @@
+use elrond_wasm::types::{Address, ManagedAddress, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::HedgingAgentsModule;
+
+const COLLATERAL_ID: &[u8] = b"WEGLD-abcdef";
+const OTHER_COLLATERAL_ID: &[u8] = b"WBTC-abcdef";
+
+#[test]
+fn position_indexes_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let other_collateral_id = TokenIdentifier::from(OTHER_COLLATERAL_ID);
+    let first_owner = ManagedAddress::from_address(&Address::from([1u8; 32]));
+    let second_owner = ManagedAddress::from_address(&Address::from([2u8; 32]));
+
+    sc.add_to_position_indexes(1, &collateral_id, &first_owner);
+    sc.add_to_position_indexes(2, &collateral_id, &second_owner);
+    sc.add_to_position_indexes(3, &other_collateral_id, &first_owner);
+    sc.add_to_position_indexes(4, &collateral_id, &first_owner);
+
+    assert_eq!(sc.get_open_positions_count(collateral_id.clone()), 3);
+    assert_eq!(
+        sc.get_open_positions(collateral_id.clone(), 0, 10)
+            .into_vec(),
+        vec![1, 2, 4]
+    );
+    assert_eq!(
+        sc.get_open_positions(collateral_id.clone(), 1, 1)
+            .into_vec(),
+        vec![2]
+    );
+    assert_eq!(sc.get_positions_of_owner_count(first_owner.clone()), 3);
+    assert_eq!(
+        sc.get_positions_of_owner(first_owner.clone(), 0, 10)
+            .into_vec(),
+        vec![1, 3, 4]
+    );
+
+    // force-closed positions still belong to their owner until withdrawn
+    sc.remove_from_open_positions(1, &collateral_id);
+    assert_eq!(
+        sc.get_open_positions(collateral_id.clone(), 0, 10)
+            .into_vec(),
+        vec![2, 4]
+    );
+    assert_eq!(sc.get_positions_of_owner_count(first_owner.clone()), 3);
+
+    sc.remove_from_position_indexes(1, &collateral_id);
+    sc.remove_from_position_indexes(2, &collateral_id);
+    assert_eq!(sc.get_open_positions_count(collateral_id.clone()), 1);
+    assert_eq!(
+        sc.get_positions_of_owner(first_owner.clone(), 0, 10)
+            .into_vec(),
+        vec![3, 4]
+    );
+    assert_eq!(sc.get_positions_of_owner_count(second_owner.clone()), 0);
+    assert!(sc.position_owner(2).is_empty());
+    assert!(sc.position_owner(4).get() == first_owner);
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{to_biguint, COLLATERAL_ID};
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::economics::math::PERCENTAGE_PRECISION;
+use stablecoin_v2::oracle::price_checks::{PriceChecksModule, PriceRound};
+use stablecoin_v2::ContractObj;
+
+const LAST_PRICE: u64 = 100_000_000;
+
+// last accepted price of $100, with a max deviation of 10%
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.max_price_deviation_percentage(&collateral_id)
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 10));
+    sc.update_last_accepted_price(&collateral_id, &to_biguint(&sc, LAST_PRICE));
+
+    sc
+}
+
+#[test]
+fn price_deviation_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    assert!(sc
+        .require_valid_price(&collateral_id, &to_biguint(&sc, 110_000_000))
+        .is_ok());
+    assert!(sc
+        .require_valid_price(&collateral_id, &to_biguint(&sc, 90_000_000))
+        .is_ok());
+    assert!(sc
+        .require_valid_price(&collateral_id, &to_biguint(&sc, 110_000_001))
+        .is_err());
+    assert!(sc
+        .require_valid_price(&collateral_id, &to_biguint(&sc, 89_999_999))
+        .is_err());
+}
+
+#[test]
+fn price_deviation_disabled_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let price = to_biguint(&sc, 2 * LAST_PRICE);
+
+    sc.max_price_deviation_percentage(&collateral_id)
+        .set(&to_biguint(&sc, 0));
+    assert!(!sc.is_price_deviation_too_high(&collateral_id, &price));
+
+    // without a last accepted price, any price is accepted
+    sc.max_price_deviation_percentage(&collateral_id)
+        .set(&to_biguint(&sc, PERCENTAGE_PRECISION / 10));
+    sc.last_accepted_price(&collateral_id).clear();
+    assert!(!sc.is_price_deviation_too_high(&collateral_id, &price));
+}
+
+#[test]
+fn round_staleness_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let round = PriceRound {
+        round_id: 1,
+        first_seen_timestamp: 0,
+    };
+
+    assert!(!sc.is_round_stale(&collateral_id, &round));
+
+    sc.max_price_age_seconds(&collateral_id).set(&60);
+    assert!(!sc.is_round_stale(&collateral_id, &round));
+}
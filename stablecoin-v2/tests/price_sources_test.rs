@@ -0,0 +1,85 @@
+mod common;
+
+use common::{to_biguint, COLLATERAL_ID};
+use elrond_wasm::types::{Address, BigUint, ManagedAddress, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::oracle::price_checks::PriceChecksModule;
+use stablecoin_v2::oracle::price_sources::{PriceSource, PriceSourceType, PriceSourcesModule};
+use stablecoin_v2::ContractObj;
+
+fn to_biguints(sc: &ContractObj<TxContext>, values: &[u64]) -> Vec<BigUint<TxContext>> {
+    values.iter().map(|value| to_biguint(sc, *value)).collect()
+}
+
+#[test]
+fn median_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+
+    assert!(sc.median(to_biguints(&sc, &[100])) == 100u64);
+    assert!(sc.median(to_biguints(&sc, &[300, 100, 200])) == 200u64);
+
+    // the two middle prices are averaged, rounding down
+    assert!(sc.median(to_biguints(&sc, &[400, 101, 100, 1_000])) == 250u64);
+    assert!(sc.median(to_biguints(&sc, &[100, 101])) == 100u64);
+}
+
+#[test]
+fn price_sources_quorum_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+
+    assert!(sc.require_valid_price_sources_quorum(2, 3).is_ok());
+    assert!(sc.require_valid_price_sources_quorum(3, 3).is_ok());
+    assert!(sc.require_valid_price_sources_quorum(4, 3).is_err());
+
+    // without sources, the aggregator set in init counts as one
+    assert!(sc.require_valid_price_sources_quorum(0, 0).is_ok());
+    assert!(sc.require_valid_price_sources_quorum(1, 0).is_ok());
+    assert!(sc.require_valid_price_sources_quorum(2, 0).is_err());
+}
+
+#[test]
+fn dex_price_sources_quorum_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+
+    assert!(sc.require_valid_dex_price_sources_quorum(0, 0).is_ok());
+    assert!(sc.require_valid_dex_price_sources_quorum(3, 1).is_ok());
+    assert!(sc.require_valid_dex_price_sources_quorum(5, 2).is_ok());
+
+    // a single DEX quote is never enough for a price
+    assert!(sc.require_valid_dex_price_sources_quorum(0, 1).is_err());
+    assert!(sc.require_valid_dex_price_sources_quorum(1, 1).is_err());
+
+    // with one aggregator and one DEX pair, the median would be the mean of both prices,
+    // so the DEX quote could move it without bound
+    assert!(sc.require_valid_dex_price_sources_quorum(2, 1).is_err());
+    assert!(sc.require_valid_dex_price_sources_quorum(4, 2).is_err());
+}
+
+// the proxy doesn't return the round's timestamp, so the age is counted from the first time the round is seen
+#[test]
+fn source_round_first_seen_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let source = PriceSource {
+        source_type: PriceSourceType::PriceAggregator,
+        address: ManagedAddress::from_address(&Address::zero()),
+    };
+    sc.max_price_age_seconds(&collateral_id).set(&60);
+
+    // a round seen for the first time is fresh, however old it really is
+    assert!(sc.update_source_round_and_check_fresh(&collateral_id, &source, 5));
+    assert!(sc.update_source_round_and_check_fresh(&collateral_id, &source, 5));
+
+    // seeing the round again doesn't reset its age
+    let last_round = sc.price_source_last_round(&collateral_id, &source).get();
+    assert_eq!(last_round.round_id, 5);
+    assert_eq!(last_round.first_seen_timestamp, 0);
+
+    assert!(sc.update_source_round_and_check_fresh(&collateral_id, &source, 6));
+    assert_eq!(
+        sc.price_source_last_round(&collateral_id, &source)
+            .get()
+            .round_id,
+        6
+    );
+}
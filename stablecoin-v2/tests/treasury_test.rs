@@ -0,0 +1,119 @@
+mod common;
+
+use common::{create_position, set_pool, to_biguint, COLLATERAL_ID, COLLATERAL_PRICE};
+use elrond_wasm::types::{Address, ManagedAddress, TokenIdentifier};
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::actors::hedging_agents::{HedgingAgentsModule, HedgingPosition};
+use stablecoin_v2::economics::fees::FeesModule;
+use stablecoin_v2::economics::pools::PoolsModule;
+use stablecoin_v2::economics::treasury::TreasuryModule;
+use stablecoin_v2::pause::PauseModule;
+use stablecoin_v2::tokens::liquidity_token::LiquidityTokenModule;
+use stablecoin_v2::ContractObj;
+
+// 600 collateral backing the stablecoins, 20 in fees and 50 owed to the liquidity providers,
+// one open position of 300 collateral with a deposit of 100 and 30 waiting to be withdrawn after a force close
+fn setup() -> ContractObj<TxContext> {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let owner = ManagedAddress::from_address(&Address::zero());
+
+    set_pool(&sc, 600, 0, 200, 300);
+    sc.accumulated_tx_fees(&collateral_id)
+        .set(&to_biguint(&sc, 20));
+    sc.liq_sft_nonce_for_collateral(&collateral_id).set(&1);
+    sc.collateral_amount_for_liq_token(1)
+        .set(&to_biguint(&sc, 50));
+    sc.pending_force_close_withdrawals(&collateral_id)
+        .set(&to_biguint(&sc, 30));
+
+    sc.set_hedging_position(1, &create_position(&sc, 100, 300));
+    sc.add_to_position_indexes(1, &collateral_id, &owner);
+
+    sc
+}
+
+#[test]
+fn owed_amount_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    // the position's claim is its deposit at the deposit price
+    assert!(sc.calculate_owed_amount(&collateral_id, &to_biguint(&sc, COLLATERAL_PRICE)) == 800u64);
+
+    // at $125, the position's claim is 100 + 300 * (1 - 100 / 125)
+    assert!(sc.calculate_owed_amount(&collateral_id, &to_biguint(&sc, 125_000_000)) == 860u64);
+
+    // losses are not counted, so at $80 the position's claim is taken as its whole deposit
+    assert!(sc.calculate_owed_amount(&collateral_id, &to_biguint(&sc, 80_000_000)) == 800u64);
+}
+
+// Netting the second position's loss against the first one's profit would only count 140 for the hedgers,
+// 50 under what the first position alone is owed
+#[test]
+fn owed_amount_underwater_position_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let owner = ManagedAddress::from_address(&Address::zero());
+
+    // opened at $200, with a deposit of 10 covering 100
+    sc.set_hedging_position(
+        2,
+        &HedgingPosition {
+            oracle_value_at_deposit_time: to_biguint(&sc, 2 * COLLATERAL_PRICE),
+            covered_value_in_stablecoin: to_biguint(&sc, 100 * 2 * COLLATERAL_PRICE),
+            ..create_position(&sc, 10, 100)
+        },
+    );
+    sc.add_to_position_indexes(2, &collateral_id, &owner);
+    sc.update_pool(&collateral_id, |pool| {
+        pool.total_collateral_covered += &to_biguint(&sc, 100);
+        pool.total_covered_value_in_stablecoin += &to_biguint(&sc, 100 * 2 * COLLATERAL_PRICE);
+    });
+
+    // at $125, the first position's claim is 160 and the second one lost more than its deposit,
+    // so the hedgers are owed at least 30 + 160.
+    // Both positions' profit is bounded as if opened at $100: 30 + 110 + 400 * (1 - 100 / 125)
+    let price = to_biguint(&sc, 125_000_000);
+    assert!(sc.calculate_hedgers_liability(&collateral_id, &price) == 220u64);
+    assert!(sc.calculate_owed_amount(&collateral_id, &price) == 890u64);
+}
+
+#[test]
+fn lowest_open_entry_price_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    let position_at_200 = HedgingPosition {
+        oracle_value_at_deposit_time: to_biguint(&sc, 2 * COLLATERAL_PRICE),
+        ..create_position(&sc, 10, 100)
+    };
+
+    assert!(sc.lowest_open_entry_price(&collateral_id).get() == COLLATERAL_PRICE);
+
+    // it's cleared once nothing is covered
+    sc.close_position(&sc.hedging_position(1).get()).unwrap();
+    assert!(sc.lowest_open_entry_price(&collateral_id).is_empty());
+
+    sc.set_hedging_position(2, &position_at_200);
+    assert!(sc.lowest_open_entry_price(&collateral_id).get() == 2 * COLLATERAL_PRICE);
+
+    sc.set_hedging_position(3, &create_position(&sc, 10, 100));
+    assert!(sc.lowest_open_entry_price(&collateral_id).get() == COLLATERAL_PRICE);
+
+    // a higher entry price doesn't raise it
+    sc.set_hedging_position(2, &position_at_200);
+    assert!(sc.lowest_open_entry_price(&collateral_id).get() == COLLATERAL_PRICE);
+}
+
+#[test]
+fn withdrawable_treasury_amount_during_settlement_test() {
+    let sc = setup();
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.treasury_balance(&collateral_id)
+        .set(&to_biguint(&sc, 100));
+
+    // the whole balance is set aside for the claims
+    sc.global_settlement_active().set(&true);
+    assert!(sc.get_withdrawable_treasury_amount(collateral_id).unwrap() == 0u64);
+    assert!(sc.require_no_global_settlement().is_err());
+}
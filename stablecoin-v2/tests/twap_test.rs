@@ -0,0 +1,111 @@
+mod common;
+
+use common::{to_biguint, COLLATERAL_ID, COLLATERAL_PRICE};
+use elrond_wasm::types::TokenIdentifier;
+use elrond_wasm_debug::TxContext;
+use stablecoin_v2::oracle::twap::{PriceObservation, TwapModule};
+use stablecoin_v2::ContractObj;
+
+fn record_observation(sc: &ContractObj<TxContext>, timestamp: u64) {
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+    sc.record_observation(
+        &collateral_id,
+        &PriceObservation {
+            timestamp,
+            price_cumulative: to_biguint(sc, timestamp * COLLATERAL_PRICE),
+        },
+    );
+}
+
+#[test]
+fn price_accumulator_first_update_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    sc.update_price_accumulator(&collateral_id, &to_biguint(&sc, COLLATERAL_PRICE));
+    sc.update_price_accumulator(&collateral_id, &to_biguint(&sc, 2 * COLLATERAL_PRICE));
+
+    // no time has passed, so the accumulator is unchanged, and the second observation is skipped
+    let accumulator = sc.price_accumulator(&collateral_id).get();
+    assert_eq!(accumulator.timestamp, 0);
+    assert!(accumulator.price_cumulative == 0u64);
+    assert!(sc.twap_last_price(&collateral_id).get() == 2 * COLLATERAL_PRICE);
+    assert_eq!(sc.price_observations(&collateral_id).len(), 1);
+}
+
+#[test]
+fn observations_min_interval_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    record_observation(&sc, 0);
+    record_observation(&sc, 59);
+    record_observation(&sc, 60);
+
+    let observations = sc.price_observations(&collateral_id);
+    assert_eq!(observations.len(), 2);
+    assert_eq!(observations.get(2).timestamp, 60);
+}
+
+#[test]
+fn observations_ring_buffer_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    for i in 0..100 {
+        record_observation(&sc, i * 60);
+    }
+    assert_eq!(sc.price_observations(&collateral_id).len(), 100);
+
+    // the oldest observations are overwritten, wrapping around at the end
+    record_observation(&sc, 6_000);
+    record_observation(&sc, 6_060);
+
+    let observations = sc.price_observations(&collateral_id);
+    assert_eq!(observations.len(), 100);
+    assert_eq!(observations.get(1).timestamp, 6_000);
+    assert_eq!(observations.get(2).timestamp, 6_060);
+    assert_eq!(observations.get(3).timestamp, 120);
+    assert_eq!(sc.oldest_observation_index(&collateral_id).get(), 3);
+
+    for i in 0..98 {
+        record_observation(&sc, 6_120 + i * 60);
+    }
+    assert_eq!(sc.oldest_observation_index(&collateral_id).get(), 1);
+    assert_eq!(
+        sc.price_observations(&collateral_id).get(100).timestamp,
+        6_120 + 97 * 60
+    );
+}
+
+#[test]
+fn twap_without_history_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+    let collateral_id = TokenIdentifier::from(COLLATERAL_ID);
+
+    assert!(sc.calculate_twap(&collateral_id, 0).is_err());
+    assert!(sc.calculate_twap(&collateral_id, 600).is_err());
+
+    // the only observation is newer than the window
+    sc.update_price_accumulator(&collateral_id, &to_biguint(&sc, COLLATERAL_PRICE));
+    assert!(sc.calculate_twap(&collateral_id, 600).is_err());
+}
+
+// 99 intervals of 60 seconds are always kept after the oldest observation is overwritten
+#[test]
+fn twap_window_validation_test() {
+    let sc = stablecoin_v2::contract_obj(TxContext::dummy());
+
+    assert!(sc.require_valid_twap_window(&to_biguint(&sc, 60)).is_ok());
+    assert!(sc
+        .require_valid_twap_window(&to_biguint(&sc, 5_940))
+        .is_ok());
+
+    assert!(sc.require_valid_twap_window(&to_biguint(&sc, 0)).is_err());
+    assert!(sc
+        .require_valid_twap_window(&to_biguint(&sc, 5_941))
+        .is_err());
+    assert!(sc
+        .require_valid_twap_window(&to_biguint(&sc, 6_001))
+        .is_err());
+}